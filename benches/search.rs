@@ -0,0 +1,135 @@
+//! Searches and facets of both backends on generated catalogs, `cargo bench` to run. Each
+//! measure is the mean time of one operation.
+
+use std::{
+    collections::HashMap,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use project_builder::{
+    CatalogBackend, Data, Filter, Project, Projects, SearchParameter, SearchTree,
+};
+
+/// Topics of the generated projects with how many values each has
+const TOPICS: [(&str, usize); 6] = [
+    ("platform", 8),
+    ("language", 20),
+    ("database", 10),
+    ("deployment", 6),
+    ("framework", 100),
+    ("ci", 5),
+];
+
+/// Deterministic pseudo-random numbers, so every run searches the same catalog
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % bound as u64) as usize
+    }
+}
+
+/// `size` projects with one or two values for most topics, and none for some
+fn catalog(size: usize) -> Projects {
+    let mut random: Random = Random(size as u64);
+    let projects: Vec<Project> = (0..size)
+        .map(|index| {
+            let mut topics: HashMap<String, Vec<String>> = HashMap::new();
+            for (topic, values) in TOPICS {
+                let mut chosen: Vec<String> = Vec::new();
+                for _ in 0..[0, 1, 1, 1, 1, 2][random.below(6)] {
+                    let value: String = format!("{topic}{}", random.below(values));
+                    if !chosen.contains(&value) {
+                        chosen.push(value);
+                    }
+                }
+                if !chosen.is_empty() {
+                    topics.insert(topic.to_string(), chosen);
+                }
+            }
+            Project {
+                source: format!("https://example.com/templates/{index}"),
+                topics,
+                ..Project::default()
+            }
+        })
+        .collect();
+    Projects {
+        projects,
+        ..Projects::default()
+    }
+}
+
+fn parameter(backend: &dyn CatalogBackend, filters: &[(&str, &str)]) -> SearchParameter {
+    let mut parameter: SearchParameter = SearchParameter::new(backend.get_topics().to_vec());
+    for (topic, value) in filters {
+        parameter
+            .topics
+            .insert(topic.to_string(), Filter::parse(value).unwrap());
+    }
+    parameter
+}
+
+/// Runs `operation` for about a second and prints its mean time
+fn measure<T>(name: &str, mut operation: impl FnMut() -> T) {
+    let start: Instant = Instant::now();
+    let mut runs: u32 = 0;
+    while runs == 0 || start.elapsed() < Duration::from_secs(1) {
+        black_box(operation());
+        runs += 1;
+    }
+    let mean: Duration = start.elapsed() / runs;
+    println!("{name:<40} {:>12.3?} ({runs} runs)", mean);
+}
+
+fn main() {
+    for size in [10_000, 50_000] {
+        println!("{size} projects");
+        measure("generate and build SearchTree", || {
+            SearchTree::new(catalog(size)).unwrap()
+        });
+        measure("generate and build Data", || {
+            Data::new(&catalog(size)).unwrap()
+        });
+        let tree: SearchTree = SearchTree::new(catalog(size)).unwrap();
+        let data: Data = Data::new(&catalog(size)).unwrap();
+        let backends: [(&str, &dyn CatalogBackend); 2] = [("SearchTree", &tree), ("Data", &data)];
+        for (name, backend) in backends {
+            let one: SearchParameter = parameter(backend, &[("language", "language3")]);
+            let three: SearchParameter = parameter(
+                backend,
+                &[
+                    ("language", "language3"),
+                    ("platform", "platform1"),
+                    ("ci", "ci2"),
+                ],
+            );
+            let alternatives: SearchParameter = parameter(
+                backend,
+                &[
+                    ("language", "language1|language2|language3"),
+                    ("platform", "!platform1|platform2"),
+                ],
+            );
+            measure(&format!("{name} search, 1 filter"), || {
+                backend.search(&one).unwrap()
+            });
+            measure(&format!("{name} search, 3 filters"), || {
+                backend.search(&three).unwrap()
+            });
+            measure(&format!("{name} search, any of and none of"), || {
+                backend.search(&alternatives).unwrap()
+            });
+            measure(&format!("{name} options of every topic"), || {
+                for topic in backend.get_topics() {
+                    black_box(backend.retrieve_topic_options(topic, &three).unwrap());
+                }
+            });
+        }
+    }
+}
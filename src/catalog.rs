@@ -0,0 +1,998 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    error::{Error, ParseError},
+    render::Variable,
+};
+
+/// A catalog of templates, as written in a catalog file or merged from several
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Projects {
+    /// Templates in catalog order, which is the order of the search results
+    pub projects: Vec<Project>,
+    /// Other spellings of topic values, see `Aliases`
+    #[serde(default, skip_serializing_if = "Aliases::is_empty")]
+    pub aliases: Aliases,
+    /// Topics the projects can have, how to show them and in which order, checked by
+    /// `validate`. Any topic is accepted if empty.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub topics: BTreeMap<String, Topic>,
+    /// Other catalogs to merge with this one, files or directories relative to this catalog
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<PathBuf>,
+}
+
+/// Languages a catalog can be written in, all with the same layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CatalogFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl CatalogFormat {
+    /// Format of a file with this extension, if it is a catalog extension
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Format of the catalog `data` read from `path`: by extension, or else JSON if it starts
+    /// like an object, TOML if it parses as TOML and YAML otherwise
+    pub fn detect(path: &Path, data: &str) -> Self {
+        if let Some(format) = Self::from_extension(path) {
+            return format;
+        }
+        if data.trim_start().starts_with('{') {
+            Self::Json
+        } else if toml::from_str::<toml::Table>(data).is_ok() {
+            Self::Toml
+        } else {
+            Self::Yaml
+        }
+    }
+}
+
+/// Every catalog file reached from the paths given to `Projects::read_all`
+#[derive(Debug, Default)]
+pub struct CatalogFiles {
+    /// Each file with its content, in the order they are merged, by canonical path
+    pub files: Vec<(PathBuf, Projects)>,
+    /// Directories listed to find the files
+    pub directories: Vec<PathBuf>,
+}
+
+impl CatalogFiles {
+    /// Files and directories read, the catalog changes when any of them does
+    pub fn paths(&self) -> Vec<&Path> {
+        self.files
+            .iter()
+            .map(|(path, _)| path.as_path())
+            .chain(self.directories.iter().map(PathBuf::as_path))
+            .collect()
+    }
+
+    /// Reads `path`, a catalog file or a directory of them, then what it includes. Files already
+    /// read are skipped, so catalogs can include each other. Paths are kept canonical, so they
+    /// stay valid as the origin of projects wherever the command runs.
+    fn read(&mut self, path: &Path, read: &mut HashSet<PathBuf>) -> Result<(), Error> {
+        let canonical: PathBuf = fs::canonicalize(path).map_err(|err| Error::io(path, err))?;
+        if !read.insert(canonical.clone()) {
+            return Ok(());
+        }
+        if canonical.is_dir() {
+            let mut files: Vec<PathBuf> = Vec::new();
+            for entry in fs::read_dir(&canonical).map_err(|err| Error::io(path, err))? {
+                let file: PathBuf = entry.map_err(|err| Error::io(path, err))?.path();
+                if CatalogFormat::from_extension(&file).is_some() && file.is_file() {
+                    files.push(file);
+                }
+            }
+            files.sort();
+            self.directories.push(canonical);
+            for file in files {
+                self.read(&file, read)?;
+            }
+            return Ok(());
+        }
+        let mut catalog: Projects = Projects::load(&canonical)?;
+        let include: Vec<PathBuf> = std::mem::take(&mut catalog.include);
+        let base: PathBuf = canonical.parent().unwrap_or(Path::new("")).to_path_buf();
+        self.files.push((canonical, catalog));
+        for included in include {
+            self.read(&base.join(included), read)?;
+        }
+        Ok(())
+    }
+}
+
+/// Declaration of a topic, written in the catalog as `[topics.language]` where `language` is the
+/// key used in the projects
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Topic {
+    /// Name shown to people, the key if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// What the topic is about, shown as help when choosing a value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Position of the topic in menus and searches, lowest first. Topics without one come after
+    /// those with one, by key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<i64>,
+    /// Values the topic accepts, in their canonical spelling. Any value is accepted if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+    /// Whether every project must have a value for the topic
+    #[serde(default)]
+    pub required: bool,
+}
+
+impl Projects {
+    /// Reads and parses the catalog at `path`
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let data: String = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+        Self::parse(path, &data)
+    }
+
+    /// Parses the content of a catalog in the format detected by `CatalogFormat::detect`, `path`
+    /// is only used for that and to locate errors
+    pub fn parse(path: &Path, data: &str) -> Result<Self, Error> {
+        let error: ParseError = match CatalogFormat::detect(path, data) {
+            CatalogFormat::Toml => match toml::from_str(data) {
+                Ok(catalog) => return Ok(catalog),
+                Err(err) => ParseError::new(path, data, err),
+            },
+            CatalogFormat::Json => match serde_json::from_str(data) {
+                Ok(catalog) => return Ok(catalog),
+                Err(err) => ParseError::json(path, err),
+            },
+            CatalogFormat::Yaml => match serde_yaml::from_str(data) {
+                Ok(catalog) => return Ok(catalog),
+                Err(err) => ParseError::yaml(path, err),
+            },
+        };
+        Err(Error::CatalogParse(error))
+    }
+
+    /// The catalog written in `format`, as `parse` reads it back
+    pub fn write(&self, format: CatalogFormat) -> Result<String, Error> {
+        let written: Result<String, String> = match format {
+            CatalogFormat::Toml => toml::to_string(self).map_err(|err| err.to_string()),
+            CatalogFormat::Json => serde_json::to_string_pretty(self)
+                .map(|json| json + "\n")
+                .map_err(|err| err.to_string()),
+            CatalogFormat::Yaml => serde_yaml::to_string(self).map_err(|err| err.to_string()),
+        };
+        written.map_err(Error::Serialize)
+    }
+
+    /// Reads every catalog reached from `paths`: files, directories of `*.toml`, `*.json`,
+    /// `*.yaml` and `*.yml` files, and what they include
+    pub fn read_all(paths: &[PathBuf]) -> Result<CatalogFiles, Error> {
+        let mut files: CatalogFiles = CatalogFiles::default();
+        let mut read: HashSet<PathBuf> = HashSet::new();
+        for path in paths {
+            files.read(path, &mut read)?;
+        }
+        Ok(files)
+    }
+
+    /// Reads and merges every catalog reached from `paths`, see `read_all` and `merge_all`
+    pub fn load_all(paths: &[PathBuf]) -> Result<Self, Error> {
+        Self::merge_all(Self::read_all(paths)?)
+    }
+
+    /// One catalog with the projects of every file, each remembering its file. Aliases are
+    /// combined, and the first file declaring a topic sets it. A source found twice is an error.
+    pub fn merge_all(files: CatalogFiles) -> Result<Self, Error> {
+        let mut merged: Self = Self::default();
+        let mut sources: HashMap<String, PathBuf> = HashMap::new();
+        for (path, catalog) in files.files {
+            for mut project in catalog.projects {
+                if let Some(first) = sources.get(&project.source) {
+                    return Err(Error::DuplicateSource(
+                        project.source,
+                        first.clone(),
+                        path.clone(),
+                    ));
+                }
+                sources.insert(project.source.clone(), path.clone());
+                project.origin = Some(path.clone());
+                merged.projects.push(project);
+            }
+            merged.merge_declarations(catalog.aliases, catalog.topics);
+        }
+        Ok(merged)
+    }
+
+    /// Adds aliases and declared topics from another catalog. Aliases are combined, a topic
+    /// already declared keeps its declaration.
+    pub fn merge_declarations(&mut self, aliases: Aliases, topics: BTreeMap<String, Topic>) {
+        for (topic, values) in aliases.0 {
+            let merged_values = self.aliases.0.entry(topic).or_default();
+            for (canonical, aliases) in values {
+                let merged_aliases: &mut Vec<String> = merged_values.entry(canonical).or_default();
+                for alias in aliases {
+                    if !merged_aliases.contains(&alias) {
+                        merged_aliases.push(alias);
+                    }
+                }
+            }
+        }
+        for (topic, declared) in topics {
+            self.topics.entry(topic).or_insert(declared);
+        }
+    }
+
+    /// Topics used by the projects in their declared order, see `Topic::order`. Topics the
+    /// `[topics]` section doesn't declare come last, by key.
+    pub fn topic_order(&self) -> Vec<String> {
+        let used: HashSet<&String> = self
+            .projects
+            .iter()
+            .flat_map(|project| project.topics.keys())
+            .collect();
+        order_topics(used.into_iter().cloned().collect(), &self.topics)
+    }
+
+    /// Replaces every aliased topic value of the projects by its canonical spelling. Done after
+    /// merging the manifests, so their topics are covered too.
+    pub fn canonicalize(&mut self) {
+        self.aliases.apply(&mut self.projects);
+    }
+}
+
+/// Sorts `topics` in the order of their declaration in `declared`, see `Topic::order`
+pub fn order_topics(mut topics: Vec<String>, declared: &BTreeMap<String, Topic>) -> Vec<String> {
+    topics.sort_by_key(|topic| {
+        let declared: Option<&Topic> = declared.get(topic);
+        let order: Option<i64> = declared.and_then(|declared| declared.order);
+        (declared.is_none(), order.is_none(), order, topic.clone())
+    });
+    topics
+}
+
+/// A value that can be chosen for a topic, with how many projects would match if it was
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TopicOption {
+    /// A value of the topic, or the spelling of `Filter::Unspecified` or
+    /// `Filter::NotApplicable`, as read by `Filter::parse`
+    pub value: String,
+    pub count: usize,
+}
+
+impl TopicOption {
+    /// The filter keeping the projects this option counts
+    pub fn filter(&self) -> Filter {
+        Filter::single(&self.value).unwrap_or_else(|| Filter::Value(self.value.clone()))
+    }
+
+    /// Options for the projects without a value for the topic, left out if no project would
+    /// match
+    pub fn without_value(unspecified: usize, not_applicable: usize) -> Vec<Self> {
+        [
+            (Filter::Unspecified, unspecified),
+            (Filter::NotApplicable, not_applicable),
+        ]
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(filter, count)| Self {
+            value: filter.to_string(),
+            count,
+        })
+        .collect()
+    }
+}
+
+impl fmt::Display for TopicOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.value, self.count)
+    }
+}
+
+/// Sorts `options` in the order `declared` lists their values, the values it doesn't list come
+/// last alphabetically
+pub fn order_values(mut options: Vec<TopicOption>, declared: Option<&Topic>) -> Vec<TopicOption> {
+    let allowed: &[String] = declared.map_or(&[], |declared| &declared.values);
+    options.sort_by_key(|option| {
+        let position: Option<usize> = allowed
+            .iter()
+            .position(|allowed| allowed.eq_ignore_ascii_case(&option.value));
+        (position.unwrap_or(usize::MAX), option.value.clone())
+    });
+    options
+}
+
+/// Other spellings of topic values, by topic then canonical value, written in the catalog as
+/// `[aliases.database] PostgreSQL = ["postgres", "pg"]`. Spellings are compared ignoring ASCII
+/// case, like the filters.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Aliases(pub BTreeMap<String, BTreeMap<String, Vec<String>>>);
+
+impl Aliases {
+    /// Whether no topic is filtered
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Canonical spelling of `value` for `topic`, `value` itself if it has none
+    pub fn canonical<'a>(&'a self, topic: &str, value: &'a str) -> &'a str {
+        let Some(values) = self.0.get(topic) else {
+            return value;
+        };
+        values
+            .iter()
+            .find(|(canonical, aliases)| {
+                canonical.eq_ignore_ascii_case(value)
+                    || aliases
+                        .iter()
+                        .any(|alias| alias.eq_ignore_ascii_case(value))
+            })
+            .map_or(value, |(canonical, _)| canonical)
+    }
+
+    /// Every canonical value and alias of `topic`
+    pub fn spellings(&self, topic: &str) -> Vec<&str> {
+        self.0
+            .get(topic)
+            .into_iter()
+            .flatten()
+            .flat_map(|(canonical, aliases)| {
+                std::iter::once(canonical.as_str()).chain(aliases.iter().map(String::as_str))
+            })
+            .collect()
+    }
+
+    /// Rewrites the topic values of `projects` to their canonical spelling, dropping the values
+    /// that end up repeated
+    pub fn apply(&self, projects: &mut [Project]) {
+        if self.is_empty() {
+            return;
+        }
+        for project in projects {
+            for (topic, values) in project.topics.iter_mut() {
+                let mut canonical: Vec<String> = Vec::with_capacity(values.len());
+                for value in values.iter() {
+                    let value: &str = self.canonical(topic, value);
+                    if !canonical
+                        .iter()
+                        .any(|known| known.eq_ignore_ascii_case(value))
+                    {
+                        canonical.push(value.to_string());
+                    }
+                }
+                *values = canonical;
+            }
+        }
+    }
+
+    /// `parameter` with every value in its canonical spelling
+    pub fn parameter(&self, parameter: &SearchParameter) -> SearchParameter {
+        let mut parameter: SearchParameter = parameter.clone();
+        for (topic, filter) in parameter.topics.iter_mut() {
+            if let Some(filter) = filter {
+                filter.map_values(&|value| self.canonical(topic, value).to_string());
+            }
+        }
+        parameter
+    }
+}
+
+/// The candidate closest to `value`, if it looks like a typo of it or its start. Case is ignored.
+pub fn suggest<'a>(value: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let value: String = value.to_lowercase();
+    candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let lowercase: String = candidate.to_lowercase();
+            let distance: usize = if lowercase.starts_with(&value) {
+                0
+            } else {
+                edit_distance(&value, &lowercase)
+            };
+            (distance <= (value.chars().count() / 3).max(1)).then_some((distance, candidate))
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+/// Levenshtein distance between `a` and `b`, counted in chars
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current: Vec<usize> = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution: usize = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// A template of the catalog. The order of search of its topics is declared in the catalog, see
+/// `Projects::topic_order`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Project {
+    /// Where the template is fetched from, a git url or a directory. Identifies the template.
+    pub source: String,
+    /// Catalog file the entry was read from, when it was read from one
+    #[serde(skip)]
+    pub origin: Option<PathBuf>,
+    /// Describes the template for people, never used as a topic
+    #[serde(flatten)]
+    pub metadata: Metadata,
+    /// Values asked for when generating, substituted in the template's placeholders
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variables: Vec<Variable>,
+    /// Values of each topic, a template can support several or none if the topic doesn't apply
+    /// to it. Must come after every other flattened field, it takes whatever key they didn't.
+    #[serde(flatten, with = "topic_values")]
+    pub topics: HashMap<String, Vec<String>>,
+}
+
+impl Project {
+    /// Name to show for the template, the source if it has none
+    pub fn title(&self) -> &str {
+        self.metadata.name.as_deref().unwrap_or(&self.source)
+    }
+
+    /// Directory of the template when the source isn't a url, see `local_source`
+    pub fn local_source(&self) -> Option<PathBuf> {
+        local_source(&self.source, self.origin.as_deref())
+    }
+}
+
+/// Directory named by `source`, `None` for a url (anything with `://`). A relative directory is
+/// relative to the catalog file it was read from like includes, or to where the command runs
+/// without one.
+pub fn local_source(source: &str, catalog: Option<&Path>) -> Option<PathBuf> {
+    if source.contains("://") {
+        return None;
+    }
+    let base: &Path = catalog.and_then(Path::parent).unwrap_or(Path::new(""));
+    Some(base.join(source))
+}
+
+/// Free-form details of a template. Their keys can't be used as topic names in the catalog.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Metadata {
+    /// Display name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Short description, a sentence or two
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintainer: Option<String>,
+    /// Keywords, unlike topics they aren't offered as filters
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// License of the template itself, like `MIT`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// When the template last changed, written as a TOML date or a string
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "date"
+    )]
+    pub last_updated: Option<String>,
+}
+
+/// Keeps TOML dates like `2024-05-01` as the text they were written as
+fn date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged, expecting = "a date or a string")]
+    enum Date {
+        Text(String),
+        Toml(toml::value::Datetime),
+    }
+
+    Ok(match Date::deserialize(deserializer)? {
+        Date::Text(text) => Some(text),
+        Date::Toml(date) => Some(date.to_string()),
+    })
+}
+
+/// Topics where each value is written as a string, or an array of strings for templates that
+/// support several. Serialized by name, so the output doesn't change between runs.
+pub mod topic_values {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(untagged, expecting = "a string or an array of strings")]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    #[derive(Serialize)]
+    #[serde(untagged)]
+    enum OneOrManyRef<'a> {
+        One(&'a String),
+        Many(&'a [String]),
+    }
+
+    pub fn serialize<S: Serializer>(
+        topics: &HashMap<String, Vec<String>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        topics
+            .iter()
+            .map(|(topic, values)| match values.as_slice() {
+                [value] => (topic, OneOrManyRef::One(value)),
+                values => (topic, OneOrManyRef::Many(values)),
+            })
+            .collect::<BTreeMap<&String, OneOrManyRef>>()
+            .serialize(serializer)
+    }
+
+    /// Topics with an empty array are kept without values, see `Filter::NotApplicable`
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<String, Vec<String>>, D::Error> {
+        let topics: HashMap<String, OneOrMany> = HashMap::deserialize(deserializer)?;
+        Ok(topics
+            .into_iter()
+            .map(|(topic, values)| match values {
+                OneOrMany::One(value) => (topic, vec![value]),
+                OneOrMany::Many(values) => (topic, values),
+            })
+            .collect())
+    }
+}
+
+/// What the projects must have for a topic to match a search, any value if there is no filter.
+///
+/// A project either has values for a topic, declares that the topic doesn't apply to it with an
+/// empty array like `platform = []`, or doesn't mention the topic at all.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// This value among those of the topic, compared ignoring ASCII case and after applying the
+    /// aliases of the catalog
+    Value(String),
+    /// No mention of the topic
+    Unspecified,
+    /// An empty array for the topic
+    NotApplicable,
+    /// Matching at least one of these filters, written `Rust|Go`. They are never `AnyOf` or
+    /// `NoneOf` themselves.
+    AnyOf(Vec<Filter>),
+    /// Matching none of these filters, written `!Vercel` or `!Vercel|Aws`. Projects not
+    /// mentioning the topic match unless `Unspecified` is one of them.
+    NoneOf(Vec<Filter>),
+}
+
+impl Filter {
+    /// Written as a filter to not filter the topic, like an empty value
+    pub const ANY: &'static str = "(any)";
+    pub const UNSPECIFIED: &'static str = "(unspecified)";
+    pub const NOT_APPLICABLE: &'static str = "(not applicable)";
+    /// Separates the filters of `AnyOf` and `NoneOf`
+    pub const OR: char = '|';
+    /// Starts a `NoneOf`
+    pub const NOT: char = '!';
+
+    /// Makes the next character part of a value, so values with `|`, a leading `!` or the
+    /// spelling of `(any)` can be written
+    pub const ESCAPE: char = '\\';
+
+    /// The filter written as `value`, `None` for `(any)` and empty values. The parenthesized
+    /// spellings are matched ignoring ASCII case.
+    ///
+    /// Fails on a negation that names nothing to leave out, like `!` or `!(any)`.
+    pub fn parse(value: &str) -> Result<Option<Self>, Error> {
+        let (negated, alternatives): (bool, &str) = match value.strip_prefix(Self::NOT) {
+            Some(alternatives) => (true, alternatives),
+            None => (false, value),
+        };
+        let mut filters: Vec<Self> = Vec::new();
+        let mut any: bool = false;
+        for (alternative, escaped) in split(alternatives) {
+            match escaped {
+                true => filters.push(Self::Value(alternative)),
+                false => match Self::single(&alternative) {
+                    Some(filter) => filters.push(filter),
+                    None => any |= !alternative.is_empty(),
+                },
+            }
+        }
+        if negated {
+            if any || filters.is_empty() {
+                return Err(Error::InvalidFilter(value.to_string()));
+            }
+            return Ok(Some(Self::NoneOf(filters)));
+        }
+        if any {
+            // Any value at all is one of them
+            return Ok(None);
+        }
+        match filters.len() {
+            0 => Ok(None),
+            1 => Ok(filters.pop()),
+            _ => Ok(Some(Self::AnyOf(filters))),
+        }
+    }
+
+    /// A filter without alternatives
+    fn single(value: &str) -> Option<Self> {
+        let is = |spelling: &str| value.eq_ignore_ascii_case(spelling);
+        if value.is_empty() || is(Self::ANY) {
+            None
+        } else if is(Self::UNSPECIFIED) {
+            Some(Self::Unspecified)
+        } else if is(Self::NOT_APPLICABLE) {
+            Some(Self::NotApplicable)
+        } else {
+            Some(Self::Value(value.to_string()))
+        }
+    }
+
+    /// Every value the filter names, to check that some project has them
+    pub fn values(&self) -> Vec<&str> {
+        match self {
+            Self::Value(value) => vec![value.as_str()],
+            Self::Unspecified | Self::NotApplicable => Vec::new(),
+            Self::AnyOf(filters) | Self::NoneOf(filters) => {
+                filters.iter().flat_map(Self::values).collect()
+            }
+        }
+    }
+
+    /// Applies `change` to every value the filter names
+    fn map_values(&mut self, change: &impl Fn(&str) -> String) {
+        match self {
+            Self::Value(value) => *value = change(value),
+            Self::Unspecified | Self::NotApplicable => {}
+            Self::AnyOf(filters) | Self::NoneOf(filters) => {
+                for filter in filters {
+                    filter.map_values(change);
+                }
+            }
+        }
+    }
+}
+
+/// The alternatives of a written filter separated by `Filter::OR`, without their escapes and
+/// with whether any was escaped
+fn split(written: &str) -> Vec<(String, bool)> {
+    let mut alternatives: Vec<(String, bool)> = vec![(String::new(), false)];
+    let mut chars = written.chars();
+    while let Some(c) = chars.next() {
+        let (alternative, escaped) = alternatives.last_mut().expect("never empty");
+        match c {
+            Filter::ESCAPE => {
+                // A trailing escape is kept as written
+                alternative.push(chars.next().unwrap_or(Filter::ESCAPE));
+                *escaped = true;
+            }
+            Filter::OR => alternatives.push((String::new(), false)),
+            c => alternative.push(c),
+        }
+    }
+    alternatives
+}
+
+/// `value` written so that `Filter::parse` reads it back as a `Filter::Value`
+fn escape(value: &str) -> String {
+    let mut written: String = String::with_capacity(value.len());
+    let spelling: bool = [Filter::ANY, Filter::UNSPECIFIED, Filter::NOT_APPLICABLE]
+        .iter()
+        .any(|spelling| value.eq_ignore_ascii_case(spelling));
+    if spelling || value.starts_with(Filter::NOT) {
+        written.push(Filter::ESCAPE);
+    }
+    for c in value.chars() {
+        if c == Filter::ESCAPE || c == Filter::OR {
+            written.push(Filter::ESCAPE);
+        }
+        written.push(c);
+    }
+    written
+}
+
+/// Writes the filters of `AnyOf` and `NoneOf` separated by `Filter::OR`
+fn alternatives(f: &mut fmt::Formatter<'_>, filters: &[Filter]) -> fmt::Result {
+    for (index, filter) in filters.iter().enumerate() {
+        if index > 0 {
+            write!(f, "{}", Filter::OR)?;
+        }
+        write!(f, "{filter}")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Value(value) => write!(f, "{}", escape(value)),
+            Self::Unspecified => write!(f, "{}", Self::UNSPECIFIED),
+            Self::NotApplicable => write!(f, "{}", Self::NOT_APPLICABLE),
+            Self::AnyOf(filters) => alternatives(f, filters),
+            Self::NoneOf(filters) => {
+                write!(f, "{}", Self::NOT)?;
+                alternatives(f, filters)
+            }
+        }
+    }
+}
+
+/// Filters of a search, one per topic
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchParameter {
+    /// What each topic must have, any value if `None`
+    pub topics: HashMap<String, Option<Filter>>,
+}
+
+impl SearchParameter {
+    /// No filter on any of the given topics
+    pub fn new(topic_list: Vec<String>) -> Self {
+        let mut topics: HashMap<String, Option<Filter>> = HashMap::with_capacity(topic_list.len());
+        for topic in topic_list {
+            topics.insert(topic, None);
+        }
+        Self { topics }
+    }
+    pub fn is_empty(&self) -> bool {
+        let mut is_empty: bool = true;
+        self.topics.values().for_each(|value| {
+            if value.is_some() {
+                is_empty = false;
+            }
+        });
+        is_empty
+    }
+}
+
+/// Where the projects of a catalog are searched: in memory with `SearchTree`, or in SQLite with
+/// `Data`. Both give the same results for the same catalog, so commands and the menu can run
+/// over either one.
+pub trait CatalogBackend {
+    /// Every topic of the projects, in their declared order
+    fn get_topics(&self) -> &[String];
+
+    fn get_aliases(&self) -> &Aliases;
+
+    /// The `[topics]` section of the catalog
+    fn get_declared(&self) -> &BTreeMap<String, Topic>;
+
+    /// Name of `topic` to show to people
+    fn label<'a>(&'a self, topic: &'a str) -> &'a str {
+        self.get_declared()
+            .get(topic)
+            .and_then(|declared| declared.label.as_deref())
+            .unwrap_or(topic)
+    }
+
+    /// Project registered with the given source, if any
+    fn get_project(&self, source: &str) -> Result<Option<Arc<Project>>, Error>;
+
+    /// Projects matching every filter of `parameter`, in catalog order
+    fn search(&self, parameter: &SearchParameter) -> Result<Vec<Arc<Project>>, Error>;
+
+    /// Projects matching every filter of `parameter` and any word of `text`, the best matches
+    /// first. Words match the start of words in the source, metadata and topic values of a
+    /// project, ignoring case. Without any word in `text` it is the same as `search`.
+    fn search_text(
+        &self,
+        parameter: &SearchParameter,
+        text: &str,
+    ) -> Result<Vec<Arc<Project>>, Error>;
+
+    /// Every value of `topic` that projects matching the filters of the other topics of
+    /// `parameter` have, with how many have it, in the order of its declaration. Values no
+    /// such project has are left out, and spellings differing only in ASCII case are one option
+    /// counting the projects of all of them.
+    fn retrieve_topic_options(
+        &self,
+        topic: &str,
+        parameter: &SearchParameter,
+    ) -> Result<Vec<TopicOption>, Error>;
+}
+
+/// Helpers shared by the tests of the backends
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn project(source: &str, topics: &[(&str, &str)]) -> Project {
+        Project {
+            source: source.to_string(),
+            topics: topics
+                .iter()
+                .map(|(topic, value)| (topic.to_string(), vec![value.to_string()]))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn option(value: &str, count: usize) -> TopicOption {
+        TopicOption {
+            value: value.to_string(),
+            count,
+        }
+    }
+
+    pub(crate) fn catalog(projects: Vec<Project>) -> Projects {
+        Projects {
+            projects,
+            ..Default::default()
+        }
+    }
+
+    /// Search of every topic of `backend`, with `filters` written as on the command line
+    pub(crate) fn parameter(
+        backend: &dyn CatalogBackend,
+        filters: &[(&str, &str)],
+    ) -> SearchParameter {
+        let mut parameter: SearchParameter = SearchParameter::new(backend.get_topics().to_vec());
+        for (topic, value) in filters {
+            parameter
+                .topics
+                .insert(topic.to_string(), Filter::parse(value).unwrap());
+        }
+        parameter
+    }
+
+    /// Sources of the projects `backend` finds with `filters`, in order
+    pub(crate) fn sources(backend: &dyn CatalogBackend, filters: &[(&str, &str)]) -> Vec<String> {
+        let found: Vec<Arc<Project>> = backend.search(&parameter(backend, filters)).unwrap();
+        found.iter().map(|project| project.source.clone()).collect()
+    }
+
+    /// Same as `sources` for a full-text search of `text`
+    pub(crate) fn text_sources(
+        backend: &dyn CatalogBackend,
+        filters: &[(&str, &str)],
+        text: &str,
+    ) -> Vec<String> {
+        let found: Vec<Arc<Project>> = backend
+            .search_text(&parameter(backend, filters), text)
+            .unwrap();
+        found.iter().map(|project| project.source.clone()).collect()
+    }
+
+    fn write(path: &Path, data: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn includes_and_directories_are_merged_once() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let root: PathBuf = fs::canonicalize(directory.path()).unwrap();
+        let main: PathBuf = root.join("main.toml");
+        let teams: PathBuf = root.join("teams");
+        write(
+            &main,
+            r#"include = ["teams", "main.toml"]
+
+            [[projects]]
+            source = "main"
+
+            [topics.language]
+            label = "Language"
+            "#,
+        );
+        write(
+            &teams.join("a.toml"),
+            r#"include = ["../main.toml"]
+
+            [[projects]]
+            source = "a"
+
+            [topics.language]
+            label = "Ignored, main.toml declares it first"
+            "#,
+        );
+        write(&teams.join("b.json"), r#"{"projects": [{"source": "b"}]}"#);
+        write(&teams.join("notes.txt"), "not a catalog");
+
+        // Kept canonical whatever the path given
+        let given: PathBuf = teams.join("..").join("main.toml");
+        let files: CatalogFiles = Projects::read_all(&[given]).unwrap();
+        assert_eq!(
+            files.paths(),
+            vec![
+                main.as_path(),
+                &teams.join("a.toml"),
+                &teams.join("b.json"),
+                &teams,
+            ]
+        );
+        let merged: Projects = Projects::merge_all(files).unwrap();
+        let sources: Vec<(&str, PathBuf)> = merged
+            .projects
+            .iter()
+            .map(|project| (project.source.as_str(), project.origin.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                ("main", main.clone()),
+                ("a", teams.join("a.toml")),
+                ("b", teams.join("b.json")),
+            ]
+        );
+        assert_eq!(merged.topics["language"].label.as_deref(), Some("Language"));
+    }
+
+    #[test]
+    fn filters_are_read_back_as_written() {
+        let value = |value: &str| Filter::Value(value.to_string());
+        let written: [(&str, Filter); 6] = [
+            ("Rust|Go", Filter::AnyOf(vec![value("Rust"), value("Go")])),
+            (
+                "!Vercel|(unspecified)",
+                Filter::NoneOf(vec![value("Vercel"), Filter::Unspecified]),
+            ),
+            ("(not applicable)", Filter::NotApplicable),
+            (r"C\|C++", value("C|C++")),
+            (r"\!important", value("!important")),
+            (
+                r"!\(any)|a\\b",
+                Filter::NoneOf(vec![value("(any)"), value(r"a\b")]),
+            ),
+        ];
+        for (written, filter) in written {
+            assert_eq!(Filter::parse(written).unwrap(), Some(filter.clone()));
+            assert_eq!(filter.to_string(), written);
+        }
+        assert_eq!(
+            Filter::parse("(NOT APPLICABLE)").unwrap(),
+            Some(Filter::NotApplicable)
+        );
+        assert_eq!(Filter::parse("").unwrap(), None);
+        assert_eq!(Filter::parse("Rust|(any)").unwrap(), None);
+    }
+
+    #[test]
+    fn negations_must_leave_something_out() {
+        for written in ["!", "!|", "!(any)", "!Vercel|(any)"] {
+            assert!(matches!(
+                Filter::parse(written),
+                Err(Error::InvalidFilter(filter)) if filter == written
+            ));
+        }
+    }
+
+    #[test]
+    fn duplicate_sources_name_both_files() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let first: PathBuf = directory.path().join("first.toml");
+        let second: PathBuf = directory.path().join("second.toml");
+        write(&first, "[[projects]]\nsource = \"a\"\n");
+        write(&second, "[[projects]]\nsource = \"a\"\n");
+        assert!(matches!(
+            Projects::load_all(&[first.clone(), second.clone()]),
+            Err(Error::DuplicateSource(source, one, other))
+                if source == "a" && one == first && other == second
+        ));
+
+        write(
+            &first,
+            "[[projects]]\nsource = \"a\"\n\n[[projects]]\nsource = \"a\"\n",
+        );
+        assert!(matches!(
+            Projects::load_all(std::slice::from_ref(&first)),
+            Err(Error::DuplicateSource(_, one, other)) if one == first && other == first
+        ));
+    }
+}
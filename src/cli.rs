@@ -0,0 +1,361 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use clap::{Args, Parser, Subcommand};
+use inquire::Confirm;
+
+use crate::{
+    catalog::{
+        CatalogBackend, CatalogFiles, CatalogFormat, Filter, Project, Projects, SearchParameter,
+        TopicOption,
+    },
+    config::{Config, Layer},
+    error::{Error, ParseError},
+    generate::{self, Generator},
+    lint::Linter,
+    manifest,
+    menu::{Menu, MenuExt},
+    output::{self, Format, TopicOptions},
+    project_sqlite::Data,
+    projects::SearchTree,
+    render::{self, Renderer},
+};
+
+/// Find templates in a catalog and generate projects from them
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Catalog of templates to read, a TOML, JSON or YAML file or a directory of them. Can be
+    /// repeated to merge several catalogs. Defaults to the configured catalogs, or `data.toml`.
+    #[arg(long, global = true)]
+    pub catalog: Vec<PathBuf>,
+    /// Keep the catalog in a database under the user's data directory, only importing the
+    /// catalog file again when it changes
+    #[arg(long, global = true)]
+    pub persistent: bool,
+    /// Format of the results [default: plain, or the configured one]
+    #[arg(long, global = true, value_enum)]
+    pub output: Option<Format>,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List the source of every template in the catalog
+    List,
+    /// Print the source of the templates matching the filters
+    Search(SearchArgs),
+    /// Show the topics and variables of a template
+    Show {
+        /// Source of the template, as written in the catalog
+        source: String,
+    },
+    /// Generate a project from a template
+    New(NewArgs),
+    /// Check the catalog against its `[topics]` section and for repeated or unreachable sources,
+    /// reporting every problem found. Exits with an error if there is any.
+    Validate,
+    /// List every topic with the values found in the catalog and how many templates have each
+    Topics(TopicsArgs),
+    /// Translate a catalog file to another format, printing it if there is no destination
+    Convert(ConvertArgs),
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the effective value of every setting and where it comes from: the system
+    /// configuration, the user's, the closest `.project-builder.toml`, a `PROJECT_BUILDER_*`
+    /// environment variable or the command line
+    Show,
+}
+
+#[derive(Debug, Args)]
+pub struct SearchArgs {
+    /// Only keep templates with this value for the topic, can be repeated. `Rust|Go` keeps those
+    /// with any of the values and `!Vercel|Aws` those with none of them. `(unspecified)` matches
+    /// those that don't mention the topic and `(not applicable)` those with an empty array for
+    /// it. Replaces the configured filter of the topic, an empty value or `(any)` removes it.
+    #[arg(long, value_name = "TOPIC=VALUE", value_parser = parse_key_value)]
+    pub filter: Vec<(String, String)>,
+    /// Words to look for in the source, name, description, tags and topic values of the
+    /// templates, the best matches first
+    #[arg(long)]
+    pub text: Option<String>,
+    /// Refine the filters with the interactive menu and print the selected template
+    #[arg(long, short)]
+    pub interactive: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct TopicsArgs {
+    /// Only count templates with this value for the topic, can be repeated, read like the
+    /// filters of `search`. The values of the filtered topic itself are counted along the other
+    /// filters.
+    #[arg(long, value_name = "TOPIC=VALUE", value_parser = parse_key_value)]
+    pub filter: Vec<(String, String)>,
+}
+
+#[derive(Debug, Args)]
+pub struct ConvertArgs {
+    /// Catalog file to translate, its includes are kept as they are
+    pub catalog: PathBuf,
+    /// File to write, overwritten if it exists
+    pub destination: Option<PathBuf>,
+    /// Format to translate to, by default the one of the destination's extension
+    #[arg(long, value_enum)]
+    pub to: Option<CatalogFormat>,
+}
+
+#[derive(Debug, Args)]
+pub struct NewArgs {
+    /// Directory the template is generated into, relative to the configured `output_dir` if
+    /// there is one
+    pub destination: PathBuf,
+    /// Source of the template, selected with the interactive menu if missing
+    #[arg(long)]
+    pub source: Option<String>,
+    /// Generate even if the destination is not empty, overwriting conflicting files
+    #[arg(long)]
+    pub force: bool,
+    /// Value of a template variable, can be repeated
+    #[arg(long = "set", value_name = "NAME=VALUE", value_parser = parse_key_value)]
+    pub set: Vec<(String, String)>,
+    /// Never prompt, variables without a value from --set take their default. Also set by the
+    /// `no_input` setting.
+    #[arg(long)]
+    pub no_input: bool,
+    /// Run the commands the template's manifest declares as hooks without asking. Otherwise they
+    /// are shown and confirmed first, and skipped with --no-input.
+    #[arg(long)]
+    pub run_hooks: bool,
+}
+
+/// Parses `key=value` arguments
+fn parse_key_value(argument: &str) -> Result<(String, String), String> {
+    match argument.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got {argument:?}")),
+    }
+}
+
+impl Cli {
+    pub fn run(self) -> Result<(), Error> {
+        let flags: Layer = Layer {
+            catalogs: (!self.catalog.is_empty()).then_some(self.catalog),
+            persistent: self.persistent.then_some(true),
+            output: self.output,
+            ..Layer::default()
+        };
+        let config: Config = Config::load(flags)?;
+        let format: Format = config.output.value;
+        let catalogs: &[PathBuf] = &config.catalogs.value;
+        match self.command {
+            Command::Config {
+                command: ConfigCommand::Show,
+            } => {
+                print!("{}", output::settings(format, &config.entries())?);
+                return Ok(());
+            }
+            // Reads the catalog file itself, to locate the problems
+            Command::Validate => return validate(catalogs, format),
+            Command::Convert(args) => return convert(args),
+            _ => {}
+        }
+        let catalog: Box<dyn CatalogBackend> = if config.persistent.value {
+            let database: PathBuf = Data::default_path(catalogs)?;
+            Box::new(Data::open(&database, catalogs)?)
+        } else {
+            let mut projects: Projects = Projects::load_all(catalogs)?;
+            manifest::merge_local(&mut projects.projects)?;
+            projects.canonicalize();
+            Box::new(SearchTree::new(projects)?)
+        };
+        match self.command {
+            Command::List => list(catalog, format),
+            Command::Search(args) => search(catalog, args, &config),
+            Command::Show { source } => show(catalog, &source, format),
+            Command::New(args) => new(catalog, args, &config),
+            Command::Topics(args) => topics(catalog, args, &config),
+            Command::Validate | Command::Convert(_) | Command::Config { .. } => {
+                unreachable!("handled before loading the catalog")
+            }
+        }
+    }
+}
+
+/// The configured filters of the given topics, replaced by the ones given on the command line.
+/// A filter with an empty value or `(any)` removes the configured one, see `Filter::parse`.
+fn filters(
+    config: &Config,
+    given: Vec<(String, String)>,
+    topics: &[String],
+) -> Result<Vec<(String, Filter)>, Error> {
+    let mut filters: HashMap<String, Filter> = HashMap::new();
+    for (topic, filter) in config.filters.iter() {
+        if let (true, Some(filter)) = (topics.contains(topic), Filter::parse(&filter.value)?) {
+            filters.insert(topic.clone(), filter);
+        }
+    }
+    for (topic, value) in given {
+        match Filter::parse(&value)? {
+            Some(filter) => filters.insert(topic, filter),
+            None => filters.remove(&topic),
+        };
+    }
+    Ok(filters.into_iter().collect())
+}
+
+fn list(catalog: Box<dyn CatalogBackend>, format: Format) -> Result<(), Error> {
+    let projects: Vec<Arc<Project>> = catalog.search(&SearchParameter::new(Vec::new()))?;
+    let projects: Vec<&Project> = projects.iter().map(Arc::as_ref).collect();
+    print!("{}", output::projects(format, &projects)?);
+    Ok(())
+}
+
+fn search(
+    catalog: Box<dyn CatalogBackend>,
+    args: SearchArgs,
+    config: &Config,
+) -> Result<(), Error> {
+    let filters: Vec<(String, Filter)> = filters(config, args.filter, catalog.get_topics())?;
+    let mut menu: Menu = Menu::new(catalog);
+    for (topic, filter) in filters {
+        menu.parameters_mut().topics.insert(topic, Some(filter));
+    }
+    *menu.text_mut() = args.text;
+    let found: Vec<Arc<Project>> = if args.interactive {
+        let source: String = menu.menu()?;
+        menu.backend().get_project(&source)?.into_iter().collect()
+    } else {
+        menu.found()?
+    };
+    let found: Vec<&Project> = found.iter().map(Arc::as_ref).collect();
+    print!("{}", output::projects(config.output.value, &found)?);
+    Ok(())
+}
+
+fn show(catalog: Box<dyn CatalogBackend>, source: &str, format: Format) -> Result<(), Error> {
+    let project: Arc<Project> = catalog
+        .get_project(source)?
+        .ok_or_else(|| Error::UnknownSource(source.to_string()))?;
+    print!("{}", output::project(format, &project)?);
+    Ok(())
+}
+
+fn new(catalog: Box<dyn CatalogBackend>, args: NewArgs, config: &Config) -> Result<(), Error> {
+    let destination: PathBuf = match &config.output_dir {
+        Some(output_dir) => output_dir.value.join(&args.destination),
+        None => args.destination,
+    };
+    let destination: &Path = &destination;
+    generate::check_destination(destination, args.force)?;
+    let filters: Vec<(String, Filter)> = filters(config, Vec::new(), catalog.get_topics())?;
+    let mut menu: Menu = Menu::new(catalog);
+    for (topic, filter) in filters {
+        menu.parameters_mut().topics.insert(topic, Some(filter));
+    }
+    let source: String = match args.source {
+        Some(source) => source,
+        None => menu.menu()?,
+    };
+    let project: Arc<Project> = menu
+        .backend()
+        .get_project(&source)?
+        .ok_or(Error::UnknownSource(source))?;
+    let generator: Generator = Generator::fetch(&project)?;
+    let given: HashMap<String, String> = args.set.into_iter().collect();
+    let prompt: bool = !args.no_input && !config.no_input.value;
+    let values = render::resolve(&generator.project().variables, given, prompt)?;
+    let renderer: Renderer = Renderer::new(values);
+    let commands: Vec<String> = generator.hook_commands(&renderer);
+    let run_hooks: bool =
+        args.run_hooks || commands.is_empty() || prompt && confirm_hooks(&commands)?;
+    let summary = generator.generate(destination, args.force, &renderer, run_hooks)?;
+    println!("{summary}");
+    Ok(())
+}
+
+/// Shows the commands a template runs after generating and asks whether to run them
+fn confirm_hooks(commands: &[String]) -> Result<bool, Error> {
+    println!("The template runs these commands in the destination after generating:");
+    for command in commands {
+        println!("  {command}");
+    }
+    Ok(Confirm::new("Run them?").with_default(false).prompt()?)
+}
+
+fn convert(args: ConvertArgs) -> Result<(), Error> {
+    let format: CatalogFormat = args
+        .to
+        .or_else(|| CatalogFormat::from_extension(args.destination.as_deref()?))
+        .ok_or(Error::MissingCatalogFormat)?;
+    let written: String = Projects::load(&args.catalog)?.write(format)?;
+    match args.destination {
+        Some(destination) => {
+            fs::write(&destination, written).map_err(|err| Error::io(&destination, err))
+        }
+        None => {
+            print!("{written}");
+            Ok(())
+        }
+    }
+}
+
+fn validate(catalogs: &[PathBuf], format: Format) -> Result<(), Error> {
+    let files: CatalogFiles = Projects::read_all(catalogs)?;
+    let mut problems: Vec<ParseError> = Vec::new();
+    let mut linter: Linter = Linter::new(&files);
+    for (path, _) in &files.files {
+        let data: String = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+        problems.extend(linter.check(path, &data)?);
+    }
+    if !problems.is_empty() {
+        print!("{}", output::problems(format, &problems)?);
+        return Err(Error::InvalidCatalog(problems.len()));
+    }
+    let catalogs: String = match files.files.as_slice() {
+        [(path, _)] => path.display().to_string(),
+        files => format!("{} catalogs", files.len()),
+    };
+    let mut projects: Projects = Projects::merge_all(files)?;
+    manifest::merge_local(&mut projects.projects)?;
+    projects.canonicalize();
+    let projects_count: usize = projects.projects.len();
+    let tree: SearchTree = SearchTree::new(projects)?;
+    println!(
+        "{catalogs}: {projects_count} projects, {} topics",
+        tree.get_topics().len()
+    );
+    Ok(())
+}
+
+fn topics(
+    catalog: Box<dyn CatalogBackend>,
+    args: TopicsArgs,
+    config: &Config,
+) -> Result<(), Error> {
+    let mut parameter: SearchParameter = SearchParameter::new(catalog.get_topics().to_vec());
+    for (topic, filter) in filters(config, args.filter, catalog.get_topics())? {
+        parameter.topics.insert(topic, Some(filter));
+    }
+    let mut found: Vec<TopicOptions> = Vec::new();
+    for topic in catalog.get_topics() {
+        let options: Vec<TopicOption> = catalog.retrieve_topic_options(topic, &parameter)?;
+        found.push(TopicOptions {
+            topic: topic.clone(),
+            options,
+        });
+    }
+    print!("{}", output::topics(config.output.value, &found)?);
+    Ok(())
+}
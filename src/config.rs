@@ -0,0 +1,382 @@
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use toml::Value;
+
+use crate::{
+    error::{Error, ParseError},
+    output::{Format, SettingEntry},
+};
+
+/// Name of the configuration file of a project, looked for in the working directory and its
+/// parents
+pub const PROJECT_FILE: &str = ".project-builder.toml";
+/// Name of the configuration file in the system and user configuration directories
+const CONFIG_FILE: &str = "config.toml";
+/// Prefix of the environment variables overriding the configuration files
+const ENV_PREFIX: &str = "PROJECT_BUILDER_";
+
+/// Where the value of a setting comes from, in increasing priority
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    System(PathBuf),
+    User(PathBuf),
+    Project(PathBuf),
+    /// With the name of the variable
+    Environment(String),
+    Flag,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::System(path) => write!(f, "system config {}", path.display()),
+            Self::User(path) => write!(f, "user config {}", path.display()),
+            Self::Project(path) => write!(f, "project config {}", path.display()),
+            Self::Environment(name) => write!(f, "environment variable {name}"),
+            Self::Flag => write!(f, "command line"),
+        }
+    }
+}
+
+/// A setting with where it comes from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Setting<T> {
+    fn default(value: T) -> Self {
+        Self {
+            value,
+            source: Source::Default,
+        }
+    }
+}
+
+/// Settings given by one layer, each one optional. This is the layout of the configuration files.
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Layer {
+    /// Catalogs read when `--catalog` isn't given
+    pub catalogs: Option<Vec<PathBuf>>,
+    /// Directory `new` generates relative destinations into
+    pub output_dir: Option<PathBuf>,
+    /// Filters applied to searches and the menu by default, by topic. Filters of topics the
+    /// catalog doesn't have are ignored.
+    #[serde(default)]
+    pub filters: BTreeMap<String, String>,
+    /// Never prompt for template variables, like `new --no-input`
+    pub no_input: Option<bool>,
+    /// Keep the catalog in a database, like `--persistent`
+    pub persistent: Option<bool>,
+    /// Format of the results, like `--output`
+    pub output: Option<Format>,
+}
+
+impl Layer {
+    /// Reads the configuration file at `path`, with its relative paths made relative to its
+    /// directory
+    fn load(path: &Path) -> Result<Self, Error> {
+        let data: String = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+        let mut layer: Self = toml::from_str(&data)
+            .map_err(|err| Error::ConfigParse(ParseError::new(path, &data, err)))?;
+        let base: &Path = path.parent().unwrap_or(Path::new(""));
+        if let Some(catalogs) = &mut layer.catalogs {
+            for catalog in catalogs.iter_mut() {
+                *catalog = base.join(&*catalog);
+            }
+        }
+        if let Some(output_dir) = &mut layer.output_dir {
+            *output_dir = base.join(&*output_dir);
+        }
+        Ok(layer)
+    }
+
+    /// Settings from the `PROJECT_BUILDER_*` environment variables: `CATALOGS` is a list of paths
+    /// separated like `PATH` and `FILTERS` a comma separated list of `topic=value`
+    fn from_env() -> Result<Self, Error> {
+        let boolean = |name: &str, value: String| match value.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" => Ok(true),
+            "0" | "false" | "no" | "" => Ok(false),
+            _ => Err(Error::InvalidEnvironment(name.to_string(), value)),
+        };
+        let mut layer: Self = Self::default();
+        for (key, value) in env::vars_os() {
+            let Some(key) = key.to_str().and_then(|key| key.strip_prefix(ENV_PREFIX)) else {
+                continue;
+            };
+            let name: String = format!("{ENV_PREFIX}{key}");
+            if key == "CATALOGS" {
+                layer.catalogs = Some(env::split_paths(&value).collect());
+                continue;
+            }
+            let value: String = value.into_string().map_err(|value| {
+                Error::InvalidEnvironment(name.clone(), value.to_string_lossy().into())
+            })?;
+            match key {
+                "OUTPUT_DIR" => layer.output_dir = Some(PathBuf::from(value)),
+                "FILTERS" => {
+                    for filter in value.split(',').filter(|filter| !filter.trim().is_empty()) {
+                        let Some((topic, value)) = filter.split_once('=') else {
+                            return Err(Error::InvalidEnvironment(name, filter.to_string()));
+                        };
+                        layer
+                            .filters
+                            .insert(topic.trim().to_string(), value.trim().to_string());
+                    }
+                }
+                "NO_INPUT" => layer.no_input = Some(boolean(&name, value)?),
+                "PERSISTENT" => layer.persistent = Some(boolean(&name, value)?),
+                "OUTPUT" => {
+                    let format: Format = Format::from_str(&value, true)
+                        .map_err(|_| Error::InvalidEnvironment(name, value))?;
+                    layer.output = Some(format);
+                }
+                _ => {}
+            }
+        }
+        Ok(layer)
+    }
+}
+
+/// Name of the environment variable for a setting
+fn env_name(key: &str) -> String {
+    format!("{ENV_PREFIX}{}", key.to_ascii_uppercase())
+}
+
+/// Settings merged from every layer, see `Config::load`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub catalogs: Setting<Vec<PathBuf>>,
+    pub output_dir: Option<Setting<PathBuf>>,
+    pub filters: BTreeMap<String, Setting<String>>,
+    pub no_input: Setting<bool>,
+    pub persistent: Setting<bool>,
+    pub output: Setting<Format>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            catalogs: Setting::default(vec![PathBuf::from("data.toml")]),
+            output_dir: None,
+            filters: BTreeMap::new(),
+            no_input: Setting::default(false),
+            persistent: Setting::default(false),
+            output: Setting::default(Format::default()),
+        }
+    }
+}
+
+impl Config {
+    /// The defaults overridden by each layer in turn: the system configuration, the user's,
+    /// the project's, the environment and then `flags`. Filters are merged by topic, a filter
+    /// with an empty value removes the one of a previous layer.
+    pub fn load(flags: Layer) -> Result<Self, Error> {
+        let mut config: Self = Self::default();
+        for (path, source) in files() {
+            if path.is_file() {
+                config.apply(Layer::load(&path)?, |_| source.clone());
+            }
+        }
+        config.apply(Layer::from_env()?, |key| Source::Environment(env_name(key)));
+        config.apply(flags, |_| Source::Flag);
+        Ok(config)
+    }
+
+    fn apply(&mut self, layer: Layer, source: impl Fn(&str) -> Source) {
+        fn set<T>(setting: &mut Setting<T>, value: Option<T>, source: Source) {
+            if let Some(value) = value {
+                *setting = Setting { value, source };
+            }
+        }
+        set(&mut self.catalogs, layer.catalogs, source("catalogs"));
+        if let Some(value) = layer.output_dir {
+            self.output_dir = Some(Setting {
+                value,
+                source: source("output_dir"),
+            });
+        }
+        for (topic, value) in layer.filters {
+            if value.is_empty() {
+                self.filters.remove(&topic);
+                continue;
+            }
+            let source: Source = source("filters");
+            self.filters.insert(topic, Setting { value, source });
+        }
+        set(&mut self.no_input, layer.no_input, source("no_input"));
+        set(&mut self.persistent, layer.persistent, source("persistent"));
+        set(&mut self.output, layer.output, source("output"));
+    }
+
+    /// Every setting with its value and source, filters as `filters.<topic>`
+    pub fn entries(&self) -> Vec<SettingEntry> {
+        fn entry<T: Into<Value>>(key: &str, value: T, source: &Source) -> SettingEntry {
+            SettingEntry {
+                key: key.to_string(),
+                value: value.into(),
+                source: source.to_string(),
+            }
+        }
+        let path = |path: &PathBuf| path.display().to_string();
+        let catalogs: Vec<String> = self.catalogs.value.iter().map(path).collect();
+        let mut entries: Vec<SettingEntry> =
+            vec![entry("catalogs", catalogs, &self.catalogs.source)];
+        if let Some(output_dir) = &self.output_dir {
+            entries.push(entry(
+                "output_dir",
+                path(&output_dir.value),
+                &output_dir.source,
+            ));
+        }
+        for (topic, filter) in &self.filters {
+            let key: String = format!("filters.{topic}");
+            entries.push(entry(&key, filter.value.as_str(), &filter.source));
+        }
+        entries.push(entry(
+            "no_input",
+            self.no_input.value,
+            &self.no_input.source,
+        ));
+        entries.push(entry(
+            "persistent",
+            self.persistent.value,
+            &self.persistent.source,
+        ));
+        let output: String = self
+            .output
+            .value
+            .to_possible_value()
+            .map_or_else(String::new, |value| value.get_name().to_string());
+        entries.push(entry("output", output, &self.output.source));
+        entries
+    }
+}
+
+/// The configuration files that are read if they exist, in increasing priority
+fn files() -> Vec<(PathBuf, Source)> {
+    let mut files: Vec<(PathBuf, Source)> = Vec::new();
+    if cfg!(unix) {
+        let path: PathBuf = Path::new("/etc")
+            .join(env!("CARGO_PKG_NAME"))
+            .join(CONFIG_FILE);
+        files.push((path.clone(), Source::System(path)));
+    }
+    if let Some(directory) = dirs::config_dir() {
+        let path: PathBuf = directory.join(env!("CARGO_PKG_NAME")).join(CONFIG_FILE);
+        files.push((path.clone(), Source::User(path)));
+    }
+    if let Ok(directory) = env::current_dir() {
+        let project = directory
+            .ancestors()
+            .map(|directory| directory.join(PROJECT_FILE))
+            .find(|path| path.is_file());
+        if let Some(path) = project {
+            files.push((path.clone(), Source::Project(path)));
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_layers_take_precedence() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let user: PathBuf = directory.path().join("user.toml");
+        fs::write(
+            &user,
+            r#"
+            catalogs = ["catalogs/main.toml"]
+            output_dir = "projects"
+            no_input = true
+
+            [filters]
+            language = "Rust"
+            platform = "Aws"
+            "#,
+        )
+        .unwrap();
+        let project: PathBuf = directory.path().join(PROJECT_FILE);
+        fs::write(
+            &project,
+            "persistent = true\n\n[filters]\nplatform = \"\"\nlicense = \"MIT\"\n",
+        )
+        .unwrap();
+
+        let mut config: Config = Config::default();
+        config.apply(Layer::load(&user).unwrap(), |_| Source::User(user.clone()));
+        config.apply(Layer::load(&project).unwrap(), |_| {
+            Source::Project(project.clone())
+        });
+        let environment: Layer = Layer {
+            no_input: Some(false),
+            output: Some(Format::Json),
+            ..Default::default()
+        };
+        config.apply(environment, |key| Source::Environment(env_name(key)));
+        let flags: Layer = Layer {
+            output: Some(Format::Table),
+            ..Default::default()
+        };
+        config.apply(flags, |_| Source::Flag);
+
+        // Relative paths of a file are relative to it
+        assert_eq!(
+            config.catalogs,
+            Setting {
+                value: vec![directory.path().join("catalogs/main.toml")],
+                source: Source::User(user.clone()),
+            }
+        );
+        assert_eq!(
+            config.output_dir.unwrap().value,
+            directory.path().join("projects")
+        );
+        assert_eq!(config.persistent.source, Source::Project(project.clone()));
+        assert_eq!(
+            config.no_input,
+            Setting {
+                value: false,
+                source: Source::Environment("PROJECT_BUILDER_NO_INPUT".to_string()),
+            }
+        );
+        assert_eq!(config.output.source, Source::Flag);
+        // Merged by topic, an empty value removes the filter
+        let filters: Vec<(&str, &str, &Source)> = config
+            .filters
+            .iter()
+            .map(|(topic, filter)| (topic.as_str(), filter.value.as_str(), &filter.source))
+            .collect();
+        assert_eq!(
+            filters,
+            vec![
+                ("language", "Rust", &Source::User(user)),
+                ("license", "MIT", &Source::Project(project)),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_an_error() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let path: PathBuf = directory.path().join("config.toml");
+        fs::write(&path, "catalog = [\"data.toml\"]\n").unwrap();
+        assert!(matches!(
+            Layer::load(&path),
+            Err(Error::ConfigParse(err)) if err.line == 1
+        ));
+    }
+}
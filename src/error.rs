@@ -1,4 +1,315 @@
-#[derive(Debug, PartialEq, Eq)]
+use std::{fmt, io, path::PathBuf};
+
+use inquire::InquireError;
+use serde::Serialize;
+
+/// Location and reason of a problem in a TOML file, like not being able to parse it
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ParseError {
+    pub path: PathBuf,
+    /// 1-based, 0 if the parser didn't report a position
+    pub line: usize,
+    /// 1-based, 0 if the parser didn't report a position
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    /// Translates the byte span reported by `toml` into a line and column of `data`
+    pub fn new(path: impl Into<PathBuf>, data: &str, err: toml::de::Error) -> Self {
+        match err.span() {
+            None => Self {
+                path: path.into(),
+                line: 0,
+                column: 0,
+                message: err.message().trim().to_string(),
+            },
+            Some(span) => Self::at(path, data, span.start, err.message().trim()),
+        }
+    }
+
+    /// Location reported by `serde_json`
+    pub fn json(path: impl Into<PathBuf>, err: serde_json::Error) -> Self {
+        Self {
+            path: path.into(),
+            line: err.line(),
+            column: err.column(),
+            message: without_location(err.to_string(), err.line(), err.column()),
+        }
+    }
+
+    /// Location reported by `serde_yaml`
+    pub fn yaml(path: impl Into<PathBuf>, err: serde_yaml::Error) -> Self {
+        let (line, column) = err
+            .location()
+            .map_or((0, 0), |location| (location.line(), location.column()));
+        Self {
+            path: path.into(),
+            line,
+            column,
+            message: without_location(err.to_string(), line, column),
+        }
+    }
+
+    /// Problem found at the byte `offset` of `data`
+    pub fn at(path: impl Into<PathBuf>, data: &str, offset: usize, message: &str) -> Self {
+        let (line, column) = Self::location(data, offset);
+        Self {
+            path: path.into(),
+            line,
+            column,
+            message: message.to_string(),
+        }
+    }
+
+    /// 1-based line and column of the byte `offset` of `data`
+    pub fn location(data: &str, offset: usize) -> (usize, usize) {
+        let before: &str = &data[..offset.min(data.len())];
+        let line_start: usize = before.rfind('\n').map_or(0, |index| index + 1);
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+}
+
+/// Removes the ` at line L column C` that `serde_json` and `serde_yaml` append to their messages
+fn without_location(message: String, line: usize, column: usize) -> String {
+    let suffix: String = format!(" at line {line} column {column}");
+    match message.find(&suffix) {
+        Some(index) => message[..index].to_string() + &message[index + suffix.len()..],
+        None => message,
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "{}: {}", self.path.display(), self.message),
+            line => write!(
+                f,
+                "{}:{line}:{}: {}",
+                self.path.display(),
+                self.column,
+                self.message
+            ),
+        }
+    }
+}
+
+/// Every way the library and the commands can fail, see `Error::exit_code`
+#[derive(Debug)]
 pub enum Error {
-    SearchNodeEmptyTopics,
+    /// Filesystem failure on the given path
+    Io(PathBuf, io::Error),
+    /// The catalog is not valid TOML or doesn't have the expected layout
+    CatalogParse(ParseError),
+    /// Failure in the SQLite backend
+    Sqlite(sqlite::Error),
+    /// The catalog database has content this version can't read, with the reason
+    CorruptDatabase(String),
+    /// There is no user data directory to keep the catalog database in
+    NoDataDirectory,
+    /// The user dismissed a prompt with Esc or Ctrl-C
+    PromptCancelled,
+    /// Asking the user for a value failed for any other reason, like not having a terminal
+    Prompt(InquireError),
+    /// A search named a topic that no project has
+    MissingTopic(String),
+    /// No project in the catalog has this source
+    UnknownSource(String),
+    /// A filter starting with `!` names nothing to leave out, with the filter as written
+    InvalidFilter(String),
+    /// A search filtered a topic by a value that no project has, with the topic, the value and
+    /// a known value it may be a misspelling of
+    UnknownFilterValue(String, String, Option<String>),
+    /// A template variable has no value and prompting is disabled
+    MissingVariable(String),
+    /// A value given for a template variable isn't accepted by it, with the name and the value
+    InvalidVariable(String, String),
+    /// Template source is neither an existing directory nor a git url
+    GenerateSourceNotFound(String),
+    /// A full-text search was made but SQLite was built without FTS5
+    NoTextSearch,
+    /// Cloning the template source failed, with the reason
+    GenerateGitClone(String, String),
+    /// Destination has content and `force` was not set
+    GenerateDestinationNotEmpty(PathBuf),
+    /// A post-generation hook failed, with the command line and the reason
+    GenerateHook(String, String),
+    /// A file or directory name of the template renders to something that isn't a single name,
+    /// like `../x`, with its path in the template and the rendered name
+    GenerateUnsafeName(String, String),
+    /// Two catalog entries have the same source, with the source and the files of both
+    DuplicateSource(String, PathBuf, PathBuf),
+    /// `validate` found problems in the catalogs, with how many
+    InvalidCatalog(usize),
+    /// The template's manifest is not valid
+    ManifestParse(ParseError),
+    /// Results could not be written in the requested output format
+    Serialize(String),
+    /// `convert` was given neither a format nor a destination with a catalog extension
+    MissingCatalogFormat,
+    /// A configuration file is not valid TOML or has unknown settings
+    ConfigParse(ParseError),
+    /// An environment variable has a value its setting doesn't accept, with the variable and
+    /// the value
+    InvalidEnvironment(String, String),
+}
+
+impl Error {
+    pub fn io(path: impl Into<PathBuf>, err: io::Error) -> Self {
+        Self::Io(path.into(), err)
+    }
+
+    /// Process exit code for this error, following the `sysexits.h` conventions where they apply
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            // EX_USAGE
+            Self::MissingTopic(_)
+            | Self::UnknownFilterValue(..)
+            | Self::InvalidFilter(_)
+            | Self::UnknownSource(_)
+            | Self::MissingVariable(_)
+            | Self::InvalidVariable(..)
+            | Self::MissingCatalogFormat => 64,
+            // EX_DATAERR
+            Self::CatalogParse(_)
+            | Self::DuplicateSource(..)
+            | Self::InvalidCatalog(..)
+            | Self::ManifestParse(_)
+            | Self::GenerateUnsafeName(..) => 65,
+            // EX_NOINPUT
+            Self::GenerateSourceNotFound(_) => 66,
+            // EX_UNAVAILABLE
+            Self::GenerateGitClone(..) | Self::NoTextSearch => 69,
+            // EX_SOFTWARE
+            Self::Sqlite(_) | Self::CorruptDatabase(_) | Self::Serialize(_) => 70,
+            // EX_CONFIG
+            Self::NoDataDirectory | Self::ConfigParse(_) | Self::InvalidEnvironment(..) => 78,
+            // EX_CANTCREAT
+            Self::GenerateDestinationNotEmpty(_) => 73,
+            // EX_IOERR
+            Self::Io(..) => 74,
+            // EX_TEMPFAIL, the hook may succeed when run again by hand
+            Self::GenerateHook(..) => 75,
+            // EX_NOPERM, there is no terminal to prompt on
+            Self::Prompt(_) => 77,
+            // Same as being interrupted by SIGINT
+            Self::PromptCancelled => 130,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            Self::CatalogParse(err) => write!(f, "invalid catalog {err}"),
+            Self::Sqlite(err) => write!(f, "catalog database: {err}"),
+            Self::CorruptDatabase(reason) => write!(
+                f,
+                "catalog database is corrupt, delete it to rebuild it: {reason}"
+            ),
+            Self::NoDataDirectory => write!(f, "no user data directory for the catalog database"),
+            Self::PromptCancelled => write!(f, "cancelled"),
+            Self::Prompt(err) => write!(f, "could not prompt: {err}"),
+            Self::MissingTopic(topic) => write!(f, "no project has the topic {topic:?}"),
+            Self::InvalidFilter(filter) => write!(
+                f,
+                "filter {filter:?} leaves out nothing, write the values to leave out after `!`"
+            ),
+            Self::UnknownFilterValue(topic, value, None) => {
+                write!(f, "no project has {value:?} as {topic}")
+            }
+            Self::UnknownFilterValue(topic, value, Some(suggestion)) => write!(
+                f,
+                "no project has {value:?} as {topic}, did you mean {suggestion:?}?"
+            ),
+            Self::UnknownSource(source) => {
+                write!(f, "no project in the catalog has source {source:?}")
+            }
+            Self::MissingVariable(name) => write!(
+                f,
+                "variable {name:?} has no default, give it a value with --set {name}=<value>"
+            ),
+            Self::InvalidVariable(name, value) => {
+                write!(f, "{value:?} is not a valid value for variable {name:?}")
+            }
+            Self::GenerateSourceNotFound(source) => write!(
+                f,
+                "template {source:?} is neither a directory nor a git url"
+            ),
+            Self::NoTextSearch => write!(
+                f,
+                "full-text search needs SQLite with FTS5, build with SQLITE_ENABLE_FTS5=1 set"
+            ),
+            Self::GenerateGitClone(source, reason) => {
+                write!(f, "could not clone {source}: {reason}")
+            }
+            Self::GenerateDestinationNotEmpty(path) => write!(
+                f,
+                "{} is not empty, use --force to generate into it anyway",
+                path.display()
+            ),
+            Self::GenerateHook(command, reason) => {
+                write!(f, "hook `{command}` failed: {reason}")
+            }
+            Self::GenerateUnsafeName(path, name) => write!(
+                f,
+                "{path} renders to {name:?}, which would be written outside its directory"
+            ),
+            Self::DuplicateSource(source, first, second) if first == second => write!(
+                f,
+                "source {source:?} is in {} more than once",
+                first.display()
+            ),
+            Self::DuplicateSource(source, first, second) => write!(
+                f,
+                "source {source:?} is in both {} and {}",
+                first.display(),
+                second.display()
+            ),
+            Self::InvalidCatalog(1) => write!(f, "found 1 problem in the catalog"),
+            Self::InvalidCatalog(count) => write!(f, "found {count} problems in the catalog"),
+            Self::ManifestParse(err) => write!(f, "invalid template manifest {err}"),
+            Self::Serialize(err) => write!(f, "could not format output: {err}"),
+            Self::MissingCatalogFormat => write!(
+                f,
+                "give the format with --to, or a destination ending in .toml, .json, .yaml or .yml"
+            ),
+            Self::ConfigParse(err) => write!(f, "invalid configuration {err}"),
+            Self::InvalidEnvironment(name, value) => {
+                write!(f, "{value:?} is not a valid value for {name}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(_, err) => Some(err),
+            Self::Sqlite(err) => Some(err),
+            Self::Prompt(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<InquireError> for Error {
+    fn from(err: InquireError) -> Self {
+        match err {
+            InquireError::OperationCanceled | InquireError::OperationInterrupted => {
+                Self::PromptCancelled
+            }
+            err => Self::Prompt(err),
+        }
+    }
+}
+
+impl From<sqlite::Error> for Error {
+    fn from(err: sqlite::Error) -> Self {
+        Self::Sqlite(err)
+    }
 }
@@ -0,0 +1,457 @@
+use std::{
+    ffi::OsString,
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tempfile::TempDir;
+
+use crate::{
+    catalog::Project,
+    error::Error,
+    manifest::{Hook, Manifest},
+    render::Renderer,
+};
+
+/// Directories that belong to the template's version control and are never copied
+const IGNORED: [&str; 1] = [".git"];
+
+/// Where the files of a template are read from
+#[derive(Debug)]
+enum Template {
+    /// A directory on the local filesystem, used in place
+    Local(PathBuf),
+    /// A git repository cloned into a temporary directory, removed when dropped
+    Cloned(TempDir),
+}
+
+impl Template {
+    /// Local paths are used directly, anything else (`file://`, `https://`, ...) is cloned with git
+    fn fetch(project: &Project) -> Result<Self, Error> {
+        let source: &str = &project.source;
+        if let Some(local) = project.local_source() {
+            if !local.is_dir() {
+                return Err(Error::GenerateSourceNotFound(source.to_string()));
+            }
+            return Ok(Self::Local(local));
+        }
+
+        let directory: TempDir =
+            TempDir::new().map_err(|err| Error::io(std::env::temp_dir(), err))?;
+        // `--` so a source starting with `-` is never read as an option of git
+        let status = Command::new("git")
+            .args(["clone", "--quiet", "--depth", "1", "--", source])
+            .arg(directory.path())
+            .status()
+            .map_err(|err| Error::GenerateGitClone(source.to_string(), err.to_string()))?;
+        if !status.success() {
+            return Err(Error::GenerateGitClone(
+                source.to_string(),
+                format!("git exited with {status}"),
+            ));
+        }
+        Ok(Self::Cloned(directory))
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            Self::Local(path) => path,
+            Self::Cloned(directory) => directory.path(),
+        }
+    }
+}
+
+/// A single file written by the generator
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WrittenFile {
+    /// Path relative to the destination directory
+    pub path: PathBuf,
+    /// If the file already existed at the destination and was replaced (only with `force`)
+    pub overwritten: bool,
+}
+
+/// A post-generation hook that ran successfully, or that wasn't run
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HookRun {
+    /// Description of the hook, or its command line
+    pub description: String,
+    /// If it was skipped because running hooks wasn't allowed
+    pub skipped: bool,
+}
+
+/// What the generator did to the destination directory
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Summary {
+    pub source: String,
+    pub destination: PathBuf,
+    pub files: Vec<WrittenFile>,
+    pub hooks: Vec<HookRun>,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Generated {} from {}",
+            self.destination.display(),
+            self.source
+        )?;
+        for file in &self.files {
+            let action = if file.overwritten {
+                "overwritten"
+            } else {
+                "created"
+            };
+            writeln!(f, "  {action:>11}  {}", file.path.display())?;
+        }
+        for hook in &self.hooks {
+            let action = if hook.skipped { "skipped" } else { "ran" };
+            writeln!(f, "  {action:>11}  {}", hook.description)?;
+        }
+        let overwritten = self.files.iter().filter(|file| file.overwritten).count();
+        write!(
+            f,
+            "{} files written ({overwritten} overwritten)",
+            self.files.len()
+        )
+    }
+}
+
+/// Refuses a destination with content unless `force` is set. A missing destination is fine.
+pub fn check_destination(destination: &Path, force: bool) -> Result<(), Error> {
+    if force || !destination.exists() {
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(destination).map_err(|err| Error::io(destination, err))?;
+    if entries.next().is_some() {
+        return Err(Error::GenerateDestinationNotEmpty(
+            destination.to_path_buf(),
+        ));
+    }
+    Ok(())
+}
+
+/// A fetched template, with the catalog entry completed by the template's manifest
+#[derive(Debug)]
+pub struct Generator {
+    template: Template,
+    project: Project,
+    manifest: Manifest,
+}
+
+impl Generator {
+    /// Retrieves the files of `project` and merges its manifest, if it has one
+    pub fn fetch(project: &Project) -> Result<Self, Error> {
+        let template: Template = Template::fetch(project)?;
+        let manifest: Manifest = Manifest::load(template.path())?;
+        let mut project: Project = project.clone();
+        manifest.merge(&mut project);
+        Ok(Self {
+            template,
+            project,
+            manifest,
+        })
+    }
+
+    /// The catalog entry merged with the manifest, its variables are the ones to ask for
+    pub fn project(&self) -> &Project {
+        &self.project
+    }
+
+    /// Command lines of the manifest's hooks as they would run, for people to review since a
+    /// fetched template can run any program
+    pub fn hook_commands(&self, renderer: &Renderer) -> Vec<String> {
+        self.manifest
+            .hooks
+            .iter()
+            .map(|hook| command(hook, renderer).join(" "))
+            .collect()
+    }
+
+    /// Materializes the template into `destination`, rendering placeholders in file contents and
+    /// names, then runs the manifest's hooks there if `run_hooks` is set.
+    ///
+    /// The destination is created if missing. If it already has content the generation is refused,
+    /// unless `force` is set, in which case conflicting files are overwritten and any other
+    /// existing files are left untouched.
+    pub fn generate(
+        &self,
+        destination: &Path,
+        force: bool,
+        renderer: &Renderer,
+        run_hooks: bool,
+    ) -> Result<Summary, Error> {
+        check_destination(destination, force)?;
+        fs::create_dir_all(destination).map_err(|err| Error::io(destination, err))?;
+
+        let mut copier: Copier = Copier {
+            manifest: &self.manifest,
+            renderer,
+            files: Vec::new(),
+        };
+        copier.copy_dir(self.template.path(), destination, "", Path::new(""))?;
+        let files: Vec<WrittenFile> = copier.files;
+
+        let mut hooks: Vec<HookRun> = Vec::with_capacity(self.manifest.hooks.len());
+        for hook in &self.manifest.hooks {
+            if run_hooks {
+                hooks.push(run_hook(hook, destination, renderer)?);
+            } else {
+                hooks.push(HookRun {
+                    description: hook
+                        .description
+                        .clone()
+                        .unwrap_or_else(|| command(hook, renderer).join(" ")),
+                    skipped: true,
+                });
+            }
+        }
+        Ok(Summary {
+            source: self.project.source.clone(),
+            destination: destination.to_path_buf(),
+            files,
+            hooks,
+        })
+    }
+}
+
+/// Program and arguments of `hook`, rendered
+fn command(hook: &Hook, renderer: &Renderer) -> Vec<String> {
+    hook.run
+        .iter()
+        .map(|argument| renderer.render(argument))
+        .collect()
+}
+
+fn run_hook(hook: &Hook, destination: &Path, renderer: &Renderer) -> Result<HookRun, Error> {
+    let run: Vec<String> = command(hook, renderer);
+    let command_line: String = run.join(" ");
+    let Some((program, arguments)) = run.split_first() else {
+        return Err(Error::GenerateHook(
+            command_line,
+            "empty command".to_string(),
+        ));
+    };
+    let status = Command::new(program)
+        .args(arguments)
+        .current_dir(destination)
+        .status()
+        .map_err(|err| Error::GenerateHook(command_line.clone(), err.to_string()))?;
+    if !status.success() {
+        return Err(Error::GenerateHook(
+            command_line,
+            format!("exited with {status}"),
+        ));
+    }
+    Ok(HookRun {
+        description: hook.description.clone().unwrap_or(command_line),
+        skipped: false,
+    })
+}
+
+/// A rendered file or directory name, which must stay a single name so nothing is written
+/// outside the destination
+fn rendered_name(template_path: &str, name: String) -> Result<String, Error> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(std::path::is_separator) {
+        return Err(Error::GenerateUnsafeName(template_path.to_string(), name));
+    }
+    Ok(name)
+}
+
+/// State of a copy from the template to the destination
+struct Copier<'a> {
+    manifest: &'a Manifest,
+    renderer: &'a Renderer,
+    files: Vec<WrittenFile>,
+}
+
+impl Copier<'_> {
+    /// Recursively copies `from` into `to`. `template_path` is the path of `from` relative to the
+    /// template root (used for exclusions), `written` the path of `to` relative to the destination.
+    fn copy_dir(
+        &mut self,
+        from: &Path,
+        to: &Path,
+        template_path: &str,
+        written: &Path,
+    ) -> Result<(), Error> {
+        let mut entries = fs::read_dir(from)
+            .map_err(|err| Error::io(from, err))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| Error::io(from, err))?;
+        // Deterministic order for the summary
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let name: OsString = entry.file_name();
+            if IGNORED.iter().any(|ignored| name == *ignored) {
+                continue;
+            }
+            let entry_path: String = match template_path {
+                "" => name.to_string_lossy().to_string(),
+                parent => format!("{parent}/{}", name.to_string_lossy()),
+            };
+            if self.manifest.is_excluded(&entry_path) {
+                continue;
+            }
+            let source: PathBuf = entry.path();
+            let name: OsString = match name.to_str() {
+                Some(name) => {
+                    OsString::from(rendered_name(&entry_path, self.renderer.render(name))?)
+                }
+                None => name,
+            };
+            let target: PathBuf = to.join(&name);
+            let written: PathBuf = written.join(&name);
+            let file_type = entry.file_type().map_err(|err| Error::io(&source, err))?;
+            if file_type.is_dir() {
+                fs::create_dir_all(&target).map_err(|err| Error::io(&target, err))?;
+                self.copy_dir(&source, &target, &entry_path, &written)?;
+            } else {
+                let overwritten: bool = target.exists();
+                self.copy_file(&source, &target)?;
+                self.files.push(WrittenFile {
+                    path: written,
+                    overwritten,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Text files are rendered, anything that isn't valid UTF-8 is copied byte for byte
+    fn copy_file(&self, source: &Path, target: &Path) -> Result<(), Error> {
+        let content: Vec<u8> = fs::read(source).map_err(|err| Error::io(source, err))?;
+        let content: Vec<u8> = match String::from_utf8(content) {
+            Ok(text) => self.renderer.render(&text).into_bytes(),
+            Err(err) => err.into_bytes(),
+        };
+        fs::write(target, content).map_err(|err| Error::io(target, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A local template with a file at its root and one in a subdirectory
+    fn template() -> TempDir {
+        let template: TempDir = TempDir::new().unwrap();
+        fs::create_dir(template.path().join("src")).unwrap();
+        fs::write(template.path().join("README.md"), "template").unwrap();
+        fs::write(template.path().join("src/main.rs"), "fn main() {}").unwrap();
+        template
+    }
+
+    fn generator(template: &TempDir) -> Generator {
+        Generator::fetch(&Project {
+            source: template.path().to_string_lossy().into_owned(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn generates_into_missing_destination() {
+        let template: TempDir = template();
+        let destination: TempDir = TempDir::new().unwrap();
+        let destination: PathBuf = destination.path().join("new");
+        let summary: Summary = generator(&template)
+            .generate(&destination, false, &Renderer::default(), false)
+            .unwrap();
+        let written: Vec<(PathBuf, bool)> = summary
+            .files
+            .into_iter()
+            .map(|file| (file.path, file.overwritten))
+            .collect();
+        assert_eq!(
+            written,
+            vec![
+                (PathBuf::from("README.md"), false),
+                (PathBuf::from("src/main.rs"), false)
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(destination.join("src/main.rs")).unwrap(),
+            "fn main() {}"
+        );
+    }
+
+    #[test]
+    fn rendered_names_stay_in_the_destination() {
+        let template: TempDir = template();
+        fs::write(template.path().join("src/{{name}}"), "").unwrap();
+        let destination: TempDir = TempDir::new().unwrap();
+        let destination: PathBuf = destination.path().join("out");
+        for name in ["../../escaped", "..", ".", "", "a/b"] {
+            let renderer: Renderer = Renderer::new([("name".to_string(), name.to_string())].into());
+            let err = generator(&template)
+                .generate(&destination, true, &renderer, false)
+                .unwrap_err();
+            assert!(
+                matches!(&err, Error::GenerateUnsafeName(path, _) if path == "src/{{name}}"),
+                "{name:?}: {err}"
+            );
+        }
+        assert!(!destination.parent().unwrap().join("escaped").exists());
+    }
+
+    #[test]
+    fn hooks_only_run_when_allowed() {
+        let template: TempDir = template();
+        fs::write(
+            template.path().join(crate::manifest::MANIFEST_FILE),
+            "[[hooks]]\nrun = [\"touch\", \"{{name}}\"]\n",
+        )
+        .unwrap();
+        let renderer: Renderer = Renderer::new([("name".to_string(), "hooked".to_string())].into());
+        let generator: Generator = generator(&template);
+        assert_eq!(generator.hook_commands(&renderer), vec!["touch hooked"]);
+
+        let destination: TempDir = TempDir::new().unwrap();
+        let summary: Summary = generator
+            .generate(destination.path(), false, &renderer, false)
+            .unwrap();
+        assert!(summary.hooks[0].skipped);
+        assert!(!destination.path().join("hooked").exists());
+
+        let summary: Summary = generator
+            .generate(destination.path(), true, &renderer, true)
+            .unwrap();
+        assert!(!summary.hooks[0].skipped);
+        assert!(destination.path().join("hooked").exists());
+    }
+
+    #[test]
+    fn non_empty_destination_needs_force() {
+        let template: TempDir = template();
+        let destination: TempDir = TempDir::new().unwrap();
+        fs::write(destination.path().join("README.md"), "mine").unwrap();
+        fs::write(destination.path().join("notes.txt"), "kept").unwrap();
+        let generator: Generator = generator(&template);
+
+        let err = generator
+            .generate(destination.path(), false, &Renderer::default(), false)
+            .unwrap_err();
+        assert!(matches!(err, Error::GenerateDestinationNotEmpty(_)));
+        assert_eq!(
+            fs::read_to_string(destination.path().join("README.md")).unwrap(),
+            "mine"
+        );
+
+        let summary: Summary = generator
+            .generate(destination.path(), true, &Renderer::default(), false)
+            .unwrap();
+        assert!(summary.files[0].overwritten);
+        assert!(!summary.files[1].overwritten);
+        assert_eq!(
+            fs::read_to_string(destination.path().join("README.md")).unwrap(),
+            "template"
+        );
+        assert_eq!(
+            fs::read_to_string(destination.path().join("notes.txt")).unwrap(),
+            "kept"
+        );
+    }
+}
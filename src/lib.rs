@@ -0,0 +1,69 @@
+//! Template discovery and project generation.
+//!
+//! A catalog lists templates with the topics they cover, like their language or database. This
+//! crate reads catalogs in TOML, JSON or YAML, searches them by topic or free text, and generates
+//! projects from the templates found. The `project-builder` binary is a thin command line over it.
+//!
+//! - [`catalog`] has the catalog model: [`Projects`] loads and merges catalog files, and
+//!   [`CatalogBackend`] is what every search backend offers.
+//! - [`SearchTree`] searches a catalog in memory, [`Data`] keeps it in SQLite, optionally on disk
+//!   so large catalogs are only imported when they change.
+//! - [`Generator`] fetches a template and renders it into a directory.
+//!
+//! ```
+//! use std::path::Path;
+//!
+//! use project_builder::{CatalogBackend, Filter, Projects, SearchParameter, SearchTree};
+//!
+//! let catalog = Projects::parse(
+//!     Path::new("catalog.toml"),
+//!     r#"
+//!     [[projects]]
+//!     source = "https://github.com/example/api"
+//!     language = "Rust"
+//!
+//!     [[projects]]
+//!     source = "https://github.com/example/site"
+//!     language = "Javascript"
+//!     "#,
+//! )?;
+//! let tree = SearchTree::new(catalog)?;
+//! let mut parameter = SearchParameter::new(tree.get_topics().to_vec());
+//! parameter
+//!     .topics
+//!     .insert("language".to_string(), Some(Filter::Value("rust".to_string())));
+//! let found = tree.search(&parameter)?;
+//! assert_eq!(found[0].source, "https://github.com/example/api");
+//! # Ok::<(), project_builder::Error>(())
+//! ```
+
+/// Catalog model, loading, merging and the search backend trait
+pub mod catalog;
+/// Command line of the `project-builder` binary
+pub mod cli;
+/// Layered configuration of the command line
+pub mod config;
+/// Errors and the exit code of each
+pub mod error;
+/// Fetching templates and rendering them into a directory
+pub mod generate;
+/// Problems found in catalog files by `validate`
+pub mod lint;
+/// Manifests templates carry in their own repository
+pub mod manifest;
+/// Interactive selection of a template
+pub mod menu;
+/// Formats of the command results
+pub mod output;
+/// Catalog backend in SQLite
+pub mod project_sqlite;
+/// Catalog backend in memory
+pub mod projects;
+/// Template variables and placeholders
+pub mod render;
+
+pub use catalog::{CatalogBackend, CatalogFormat, Filter, Project, Projects, SearchParameter};
+pub use error::Error;
+pub use generate::Generator;
+pub use project_sqlite::Data;
+pub use projects::SearchTree;
@@ -0,0 +1,341 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use serde::Deserialize;
+use toml::{Spanned, Value};
+
+use crate::{
+    catalog::{self, CatalogFiles, CatalogFormat, Projects, Topic},
+    error::{Error, ParseError},
+};
+
+/// A catalog entry as written, with the location of each key and value
+type Entry = BTreeMap<Spanned<String>, Spanned<Value>>;
+
+/// The catalog as written, only to locate problems in TOML catalogs
+#[derive(Deserialize)]
+struct Layout {
+    #[serde(default)]
+    projects: Vec<Spanned<Entry>>,
+}
+
+/// Start of the value of `key` in `entry`, or of the key if the entry doesn't have it. `None`
+/// without a layout to locate it in.
+fn offset(entry: Option<&Spanned<Entry>>, key: &str) -> Option<usize> {
+    let entry: &Spanned<Entry> = entry?;
+    let offset: usize = entry
+        .get_ref()
+        .iter()
+        .find(|(name, _)| name.get_ref() == key)
+        .map_or(entry.span().start, |(_, value)| value.span().start);
+    Some(offset)
+}
+
+/// Start of `key` itself in `entry`
+fn key_offset(entry: Option<&Spanned<Entry>>, key: &str) -> Option<usize> {
+    let entry: &Spanned<Entry> = entry?;
+    let offset: usize = entry
+        .get_ref()
+        .keys()
+        .find(|name| name.get_ref() == key)
+        .map_or(entry.span().start, |name| name.span().start);
+    Some(offset)
+}
+
+/// Why `source` of the catalog file `catalog` can't be fetched, if it can't. Sources with `://`
+/// are cloned with git, any other must be a directory, see `catalog::local_source`.
+fn check_source(source: &str, catalog: &Path) -> Option<String> {
+    let Some((scheme, rest)) = source.split_once("://") else {
+        if catalog::local_source(source, Some(catalog)).is_some_and(|path| path.is_dir()) {
+            return None;
+        }
+        return Some(format!(
+            "source {source:?} is neither a url nor a directory"
+        ));
+    };
+    let valid_scheme: bool = scheme
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    // Only file urls have an empty host
+    let host: &str = rest.split('/').next().unwrap_or_default();
+    let valid_host: bool = !host.is_empty() || (scheme == "file" && rest.len() > 1);
+    if !valid_scheme || !valid_host || source.chars().any(char::is_whitespace) {
+        return Some(format!("malformed url {source:?}"));
+    }
+    None
+}
+
+/// Checks catalog files one after the other, against the topics and aliases they declare
+/// together
+pub struct Linter {
+    /// Aliases and declared topics of every catalog
+    declarations: Projects,
+    /// How many projects of every catalog use each topic
+    usage: HashMap<String, usize>,
+    /// Location of the first entry with each source checked so far
+    sources: HashMap<String, String>,
+}
+
+impl Linter {
+    pub fn new(files: &CatalogFiles) -> Self {
+        let mut declarations: Projects = Projects::default();
+        let mut usage: HashMap<String, usize> = HashMap::new();
+        for (_, catalog) in &files.files {
+            declarations.merge_declarations(catalog.aliases.clone(), catalog.topics.clone());
+            for project in &catalog.projects {
+                for topic in project.topics.keys() {
+                    *usage.entry(topic.clone()).or_default() += 1;
+                }
+            }
+        }
+        Self {
+            declarations,
+            usage,
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Every problem found in the catalog `data` read from `path`, in the order they appear in
+    /// it: topics or values that the `[topics]` sections don't declare, missing required topics,
+    /// sources already seen and sources that can't be fetched. Without any `[topics]` section, a
+    /// topic only used once with a name close to another topic is reported as a likely typo.
+    ///
+    /// Problems are only located in TOML catalogs, the line and column are 0 in the others.
+    ///
+    /// Fails only if the catalog can't be parsed at all.
+    pub fn check(&mut self, path: &Path, data: &str) -> Result<Vec<ParseError>, Error> {
+        let catalog: Projects = Projects::parse(path, data)?;
+        let layout: Layout = match CatalogFormat::detect(path, data) {
+            CatalogFormat::Toml => toml::from_str(data)
+                .map_err(|err| Error::CatalogParse(ParseError::new(path, data, err)))?,
+            CatalogFormat::Json | CatalogFormat::Yaml => Layout {
+                projects: Vec::new(),
+            },
+        };
+        let problem = |offset: Option<usize>, message: String| match offset {
+            Some(offset) => ParseError::at(path, data, offset, &message),
+            None => ParseError {
+                path: path.to_path_buf(),
+                line: 0,
+                column: 0,
+                message,
+            },
+        };
+        let declared: &BTreeMap<String, Topic> = &self.declarations.topics;
+        let mut problems: Vec<ParseError> = Vec::new();
+
+        for (index, project) in catalog.projects.iter().enumerate() {
+            let entry: Option<&Spanned<Entry>> = layout.projects.get(index);
+            let source_offset: Option<usize> = offset(entry, "source");
+            if let Some(reason) = check_source(&project.source, path) {
+                problems.push(problem(source_offset, reason));
+            }
+            match self.sources.get(&project.source) {
+                Some(first) => problems.push(problem(
+                    source_offset,
+                    format!(
+                        "source {:?} is already used by the entry at {first}",
+                        project.source
+                    ),
+                )),
+                None => {
+                    let location: String = match source_offset {
+                        Some(source_offset) => {
+                            let (line, _) = ParseError::location(data, source_offset);
+                            format!("{}:{line}", path.display())
+                        }
+                        None => path.display().to_string(),
+                    };
+                    self.sources.insert(project.source.clone(), location);
+                }
+            }
+
+            for (topic, values) in project.topics.iter() {
+                let at: Option<usize> = key_offset(entry, topic);
+                if declared.is_empty() {
+                    // Topics of data not given to `Linter::new` have no usage to compare
+                    if self.usage.get(topic) != Some(&1) {
+                        continue;
+                    }
+                    let others = self
+                        .usage
+                        .iter()
+                        .filter(|(other, count)| **count > 1 && *other != topic)
+                        .map(|(other, _)| other.as_str());
+                    if let Some(suggestion) = catalog::suggest(topic, others) {
+                        let message: String = format!(
+                            "topic {topic:?} is only used here, did you mean {suggestion:?}?"
+                        );
+                        problems.push(problem(at, message));
+                    }
+                    continue;
+                }
+                let Some(topic_declaration) = declared.get(topic) else {
+                    let names = declared.keys().map(String::as_str);
+                    let message: String = match catalog::suggest(topic, names) {
+                        Some(suggestion) => {
+                            format!("unknown topic {topic:?}, did you mean {suggestion:?}?")
+                        }
+                        None => format!("unknown topic {topic:?}"),
+                    };
+                    problems.push(problem(at, message));
+                    continue;
+                };
+                if topic_declaration.values.is_empty() {
+                    continue;
+                }
+                for value in values {
+                    let canonical: &str = self.declarations.aliases.canonical(topic, value);
+                    let allowed: &[String] = &topic_declaration.values;
+                    if allowed
+                        .iter()
+                        .any(|allowed| allowed.eq_ignore_ascii_case(canonical))
+                    {
+                        continue;
+                    }
+                    let allowed = allowed.iter().map(String::as_str);
+                    let message: String = format!("{value:?} is not a value of topic {topic:?}");
+                    let message: String = match catalog::suggest(canonical, allowed) {
+                        Some(suggestion) => format!("{message}, did you mean {suggestion:?}?"),
+                        None => message,
+                    };
+                    problems.push(problem(offset(entry, topic), message));
+                }
+            }
+
+            for (topic, topic_declaration) in declared.iter() {
+                if topic_declaration.required && !project.topics.contains_key(topic) {
+                    problems.push(problem(
+                        source_offset,
+                        format!(
+                            "{:?} doesn't have the required topic {topic:?}",
+                            project.source
+                        ),
+                    ));
+                }
+            }
+        }
+
+        problems.sort_by_key(|problem| (problem.line, problem.column));
+        Ok(problems)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATALOG: &str = r#"[topics.language]
+values = ["Rust", "Go"]
+[topics.platform]
+required = true
+[[projects]]
+source = "https://example.com/a"
+language = "Rustt"
+platfrom = "Aws"
+[[projects]]
+source = "missing-directory"
+language = ["Go", "rust"]
+"#;
+
+    const MORE: &str = r#"{"projects": [{"source": "https://example.com/a", "platform": "Aws"}]}"#;
+
+    fn problems(problems: Vec<ParseError>) -> Vec<(usize, usize, String)> {
+        problems
+            .into_iter()
+            .map(|problem| (problem.line, problem.column, problem.message))
+            .collect()
+    }
+
+    #[test]
+    fn problems_are_located_in_order() {
+        let (path, more): (&Path, &Path) = (Path::new("catalog.toml"), Path::new("more.json"));
+        let files: CatalogFiles = CatalogFiles {
+            files: vec![
+                (path.to_path_buf(), Projects::parse(path, CATALOG).unwrap()),
+                (more.to_path_buf(), Projects::parse(more, MORE).unwrap()),
+            ],
+            directories: Vec::new(),
+        };
+        let mut linter: Linter = Linter::new(&files);
+        assert_eq!(
+            problems(linter.check(path, CATALOG).unwrap()),
+            vec![
+                (
+                    6,
+                    10,
+                    r#""https://example.com/a" doesn't have the required topic "platform""#
+                        .to_string()
+                ),
+                (
+                    7,
+                    12,
+                    r#""Rustt" is not a value of topic "language", did you mean "Rust"?"#
+                        .to_string()
+                ),
+                (
+                    8,
+                    1,
+                    r#"unknown topic "platfrom", did you mean "platform"?"#.to_string()
+                ),
+                (
+                    10,
+                    10,
+                    r#"source "missing-directory" is neither a url nor a directory"#.to_string()
+                ),
+                (
+                    10,
+                    10,
+                    r#""missing-directory" doesn't have the required topic "platform""#.to_string()
+                ),
+            ]
+        );
+
+        // Not located outside TOML, but the first entry with the source is
+        assert_eq!(
+            problems(linter.check(more, MORE).unwrap()),
+            vec![(
+                0,
+                0,
+                r#"source "https://example.com/a" is already used by the entry at catalog.toml:6"#
+                    .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn rare_topics_are_likely_typos_without_declarations() {
+        let path: &Path = Path::new("catalog.toml");
+        let data: &str = r#"[[projects]]
+source = "https://example.com/a"
+language = "Rust"
+[[projects]]
+source = "https://example.com/b"
+language = "Go"
+[[projects]]
+source = "https://example.com/c"
+langauge = "Go"
+"#;
+        let files: CatalogFiles = CatalogFiles {
+            files: vec![(path.to_path_buf(), Projects::parse(path, data).unwrap())],
+            directories: Vec::new(),
+        };
+        assert_eq!(
+            problems(Linter::new(&files).check(path, data).unwrap()),
+            vec![(
+                9,
+                1,
+                r#"topic "langauge" is only used here, did you mean "language"?"#.to_string()
+            )]
+        );
+        // Data the linter wasn't built from only lacks the usage of its topics
+        let mut linter: Linter = Linter::new(&CatalogFiles::default());
+        assert!(linter.check(path, data).unwrap().is_empty());
+    }
+}
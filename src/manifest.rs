@@ -0,0 +1,207 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    catalog::{self, Project},
+    error::{Error, ParseError},
+    render::Variable,
+};
+
+/// Name of the manifest file at the root of a template
+pub const MANIFEST_FILE: &str = "project-builder.toml";
+
+/// Metadata a template carries about itself, read from `project-builder.toml`:
+///
+/// ```toml
+/// exclude = ["docs/*", "*.png"]
+///
+/// [topics]
+/// language = "Rust"
+///
+/// [[variables]]
+/// name = "project_name"
+/// type = "text"
+///
+/// [[hooks]]
+/// run = ["git", "init"]
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default, with = "catalog::topic_values")]
+    pub topics: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub variables: Vec<Variable>,
+    /// Paths relative to the template root that are not copied, `*` matches any characters
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Commands run in the destination directory after the files are written
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+}
+
+/// A command run after generation, without a shell. Arguments are rendered like file contents.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    /// Program followed by its arguments
+    pub run: Vec<String>,
+    /// Shown instead of the command in the summary
+    pub description: Option<String>,
+}
+
+impl Manifest {
+    /// Reads the manifest at the root of `template`, a template without one has an empty manifest
+    pub fn load(template: &Path) -> Result<Self, Error> {
+        let path = template.join(MANIFEST_FILE);
+        let data: String = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(Error::io(path, err)),
+        };
+        toml::from_str(&data).map_err(|err| Error::ManifestParse(ParseError::new(path, &data, err)))
+    }
+
+    /// Completes a catalog entry with this manifest.
+    ///
+    /// The catalog entry wins on conflicts, so a catalog can override the defaults of a template
+    /// it doesn't own: topics and variables are only added if the entry doesn't declare them.
+    pub fn merge(&self, project: &mut Project) {
+        for (topic, value) in &self.topics {
+            project
+                .topics
+                .entry(topic.clone())
+                .or_insert_with(|| value.clone());
+        }
+        for variable in &self.variables {
+            if !project
+                .variables
+                .iter()
+                .any(|declared| declared.name == variable.name)
+            {
+                project.variables.push(variable.clone());
+            }
+        }
+    }
+
+    /// If the path (relative to the template root, `/` separated) must not be copied
+    pub fn is_excluded(&self, path: &str) -> bool {
+        path == MANIFEST_FILE || self.exclude.iter().any(|pattern| matches(pattern, path))
+    }
+}
+
+/// Templates with a local source have their manifest merged when the catalog is loaded, so its
+/// topics are searchable. Remote templates are only merged once fetched for generation.
+///
+/// Returns what the merged data depends on besides the catalogs: the directory of each local
+/// template, and its manifest if it has one.
+pub fn merge_local(projects: &mut [Project]) -> Result<Vec<PathBuf>, Error> {
+    let mut read: Vec<PathBuf> = Vec::new();
+    for project in projects {
+        let Some(path) = project.local_source().filter(|path| path.is_dir()) else {
+            continue;
+        };
+        Manifest::load(&path)?.merge(project);
+        let manifest: PathBuf = path.join(MANIFEST_FILE);
+        if manifest.is_file() {
+            read.push(manifest);
+        }
+        read.push(path);
+    }
+    Ok(read)
+}
+
+/// Glob matching where `*` matches any sequence of characters and `?` any single one
+fn matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.trim_matches('/').chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    // Position in each after the last `*`, to backtrack to
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut s) = (0, 0);
+    while s < path.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == path[s]) {
+            p += 1;
+            s += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, s));
+            p += 1;
+        } else if let Some((star_p, star_s)) = star {
+            p = star_p;
+            s = star_s + 1;
+            star = Some((star_p, star_s + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_match_whole_paths() {
+        assert!(matches("*.png", "logo.png"));
+        assert!(matches("*.png", "docs/logo.png"));
+        assert!(!matches("*.png", "logo.png.txt"));
+        assert!(matches("docs/*", "docs/guide/intro.md"));
+        assert!(matches("/docs/", "docs"));
+        assert!(!matches("docs", "docs/guide.md"));
+        assert!(matches("file?.txt", "file1.txt"));
+        assert!(!matches("file?.txt", "file.txt"));
+        assert!(matches("*a*b", "xaxxb"));
+        assert!(!matches("*a*b", "xaxxbc"));
+        assert!(matches("**", ""));
+    }
+
+    #[test]
+    fn manifest_is_excluded_and_merged_under_the_catalog() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+            exclude = ["target/*"]
+
+            [topics]
+            language = "Rust"
+            platform = "Aws"
+            "#,
+        )
+        .unwrap();
+        assert!(manifest.is_excluded(MANIFEST_FILE));
+        assert!(manifest.is_excluded("target/debug/app"));
+        assert!(!manifest.is_excluded("src/main.rs"));
+
+        let mut project: Project = Project {
+            source: "template".to_string(),
+            topics: [("platform".to_string(), vec!["Vercel".to_string()])].into(),
+            ..Default::default()
+        };
+        manifest.merge(&mut project);
+        assert_eq!(project.topics["language"], vec!["Rust"]);
+        assert_eq!(project.topics["platform"], vec!["Vercel"]);
+    }
+
+    #[test]
+    fn local_sources_are_relative_to_their_catalog() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let template: PathBuf = directory.path().join("template");
+        fs::create_dir(&template).unwrap();
+        fs::write(
+            template.join(MANIFEST_FILE),
+            "[topics]\nlanguage = \"Rust\"\n",
+        )
+        .unwrap();
+        let catalog: PathBuf = directory.path().join("catalog.toml");
+        fs::write(&catalog, "[[projects]]\nsource = \"template\"\n").unwrap();
+
+        let mut projects = catalog::Projects::load_all(&[catalog]).unwrap();
+        merge_local(&mut projects.projects).unwrap();
+        assert_eq!(projects.projects[0].local_source(), Some(template));
+        assert_eq!(projects.projects[0].topics["language"], vec!["Rust"]);
+    }
+}
@@ -1,23 +1,86 @@
-use inquire::Select;
+use std::{fmt, sync::Arc};
+
+use inquire::{MultiSelect, Select, Text};
 
 use crate::{
+    catalog::{CatalogBackend, Filter, Project, SearchParameter, TopicOption},
     error::Error,
-    projects::{Projects, SearchParameter, SearchTree},
 };
 
-const CLEAR_STRING: &str = "(clear)";
+/// An entry of the main menu
+enum Choice {
+    /// A topic with its label and current filter, selected to change it
+    Filter {
+        topic: String,
+        label: String,
+        filter: Option<Filter>,
+    },
+    /// The words of the full-text search, selected to change them
+    Text(Option<String>),
+    Project(Arc<Project>),
+}
 
-#[derive(Debug, PartialEq, Eq)]
+/// An entry of the menu of a topic's values
+enum ValueChoice {
+    /// A value, or the projects without one, with how many projects would match once it's
+    /// chosen
+    Value(TopicOption),
+    /// Opens a choice of several values, keeping the projects with any of them
+    AnyOf,
+    /// Opens a choice of several values, keeping the projects with none of them
+    NoneOf,
+    /// Removes the filter of the topic
+    Any,
+}
+
+impl fmt::Display for ValueChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Value(option) => write!(f, "{option}"),
+            Self::AnyOf => write!(f, "(any of...)"),
+            Self::NoneOf => write!(f, "(none of...)"),
+            Self::Any => write!(f, "{}", Filter::ANY),
+        }
+    }
+}
+
+impl fmt::Display for Choice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Filter { label, filter, .. } => match filter {
+                Some(filter) => write!(f, "{label}: {filter}"),
+                None => write!(f, "{label}: "),
+            },
+            Self::Text(text) => write!(f, "(search text): {}", text.as_deref().unwrap_or_default()),
+            Self::Project(project) => {
+                write!(f, "{}", project.title())?;
+                if let Some(description) = &project.metadata.description {
+                    write!(f, " - {description}")?;
+                }
+                if !project.metadata.tags.is_empty() {
+                    write!(f, " [{}]", project.metadata.tags.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Interactive menu over a catalog, refining the filters until a template is selected
 pub struct Menu {
     parameters: SearchParameter,
-    tree: SearchTree,
+    backend: Box<dyn CatalogBackend>,
+    text: Option<String>,
 }
 
 impl Menu {
-    pub fn new(projects: Projects) -> Result<Self, Error> {
-        let tree: SearchTree = SearchTree::new(projects.projects)?;
-        let parameters: SearchParameter = SearchParameter::new(tree.get_topics().to_owned());
-        Ok(Self { parameters, tree })
+    pub fn new(backend: Box<dyn CatalogBackend>) -> Self {
+        let parameters: SearchParameter = SearchParameter::new(backend.get_topics().to_owned());
+        Self {
+            parameters,
+            backend,
+            text: None,
+        }
     }
 }
 
@@ -30,88 +93,171 @@ impl MenuExt for Menu {
         &mut self.parameters
     }
 
-    fn tree(&self) -> &SearchTree {
-        &self.tree
+    fn backend(&self) -> &dyn CatalogBackend {
+        self.backend.as_ref()
+    }
+
+    fn text(&self) -> Option<&str> {
+        self.text.as_deref()
     }
-}
 
-pub const OPTIONS: [&str; 5] = ["Source", "Platform", "Language", "Database", "Deployment"];
+    fn text_mut(&mut self) -> &mut Option<String> {
+        &mut self.text
+    }
+}
 
 fn parameters_update(
-    topic: &String,
-    tree: &SearchTree,
-    parameter: SearchParameter,
-) -> Option<String> {
-    println!("Function parameters_update\ntopic:{topic:?}\nparameter:{parameter:?}\n");
-    let mut options: Vec<String> = tree
-        .retrieve_topic_options(topic, parameter)
-        .into_iter()
-        .collect();
-    options.push(CLEAR_STRING.to_string());
-    let selected: String = Select::new(
-        &format!("Select which type of {topic:?} to filter: "),
-        options,
-    )
-    .prompt()
-    .unwrap();
-    if selected == CLEAR_STRING {
-        return None;
+    topic: &str,
+    backend: &dyn CatalogBackend,
+    parameter: &SearchParameter,
+) -> Result<Option<Filter>, Error> {
+    let values: Vec<TopicOption> = backend.retrieve_topic_options(topic, parameter)?;
+    let mut options: Vec<ValueChoice> = values.iter().cloned().map(ValueChoice::Value).collect();
+    options.extend([ValueChoice::AnyOf, ValueChoice::NoneOf, ValueChoice::Any]);
+    let label: &str = backend.label(topic);
+    let message: String = format!("Select which {label} to filter: ");
+    let mut select: Select<ValueChoice> = Select::new(&message, options);
+    let description: Option<&str> = backend
+        .get_declared()
+        .get(topic)
+        .and_then(|declared| declared.description.as_deref());
+    if let Some(description) = description {
+        select = select.with_help_message(description);
+    }
+    let current: Option<&Filter> = parameter.topics.get(topic).and_then(Option::as_ref);
+    match select.prompt()? {
+        ValueChoice::Value(option) => Ok(Some(option.filter())),
+        ValueChoice::AnyOf => {
+            let selected: &[Filter] = match current {
+                Some(Filter::AnyOf(filters)) => filters,
+                Some(filter) => std::slice::from_ref(filter),
+                None => &[],
+            };
+            let message: String = format!("Keep the projects with any of these {label}: ");
+            let mut filters: Vec<Filter> = select_many(&message, values, selected)?;
+            Ok(match filters.len() {
+                0 => None,
+                1 => filters.pop(),
+                _ => Some(Filter::AnyOf(filters)),
+            })
+        }
+        ValueChoice::NoneOf => {
+            let selected: &[Filter] = match current {
+                Some(Filter::NoneOf(filters)) => filters,
+                _ => &[],
+            };
+            let message: String = format!("Leave out the projects with any of these {label}: ");
+            let filters: Vec<Filter> = select_many(&message, values, selected)?;
+            Ok((!filters.is_empty()).then_some(Filter::NoneOf(filters)))
+        }
+        ValueChoice::Any => Ok(None),
     }
-    Some(selected)
 }
 
+/// Filters of the options checked with a `MultiSelect`, starting with those of `selected`
+/// checked
+fn select_many(
+    message: &str,
+    options: Vec<TopicOption>,
+    selected: &[Filter],
+) -> Result<Vec<Filter>, Error> {
+    let default: Vec<usize> = checked_by_default(&options, selected);
+    let checked: Vec<TopicOption> = MultiSelect::new(message, options)
+        .with_default(&default)
+        .prompt()?;
+    Ok(checked.iter().map(TopicOption::filter).collect())
+}
+
+/// Positions of the options the filters of `selected` keep, values compared ignoring ASCII case
+/// like searches
+fn checked_by_default(options: &[TopicOption], selected: &[Filter]) -> Vec<usize> {
+    let keeps = |filter: &Filter, option: &TopicOption| match (filter, option.filter()) {
+        (Filter::Value(value), Filter::Value(option)) => value.eq_ignore_ascii_case(&option),
+        (filter, option) => *filter == option,
+    };
+    options
+        .iter()
+        .enumerate()
+        .filter(|(_, option)| selected.iter().any(|filter| keeps(filter, option)))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// The menu, over any state giving the filters, the catalog and the search text
 pub trait MenuExt {
     fn parameters(&self) -> &SearchParameter;
     fn parameters_mut(&mut self) -> &mut SearchParameter;
-    fn tree(&self) -> &SearchTree;
-
-    fn sources(&self) -> Vec<String> {
-        let projects = self.tree().get_projects();
-        let mut sources: Vec<String> = Vec::with_capacity(projects.len());
-        projects
-            .iter()
-            .for_each(|project| sources.push(project.source.clone()));
-        sources
+    /// Catalog searched, in memory or in the database
+    fn backend(&self) -> &dyn CatalogBackend;
+    /// Words of the full-text search, if any
+    fn text(&self) -> Option<&str>;
+    fn text_mut(&mut self) -> &mut Option<String>;
+
+    /// Projects matching the filters and the search text, the best matches first
+    fn found(&self) -> Result<Vec<Arc<Project>>, Error> {
+        match self.text() {
+            Some(text) => self.backend().search_text(self.parameters(), text),
+            None => self.backend().search(self.parameters()),
+        }
     }
 
-    fn menu(&mut self) -> Result<String, String> {
+    fn menu(&mut self) -> Result<String, Error> {
         loop {
-            let mut options: Vec<String> = Vec::with_capacity(4 + self.sources().len());
-            for (topic, value) in self.parameters().topics.iter() {
-                let value = match value {
-                    None => "".to_string(),
-                    Some(value) => value.to_string(),
-                };
-                let option = format!("{topic}: {value}");
-                options.push(option);
+            let found: Vec<Arc<Project>> = self.found()?;
+            let topics: &[String] = self.backend().get_topics();
+            let mut options: Vec<Choice> = Vec::with_capacity(1 + topics.len() + found.len());
+            options.push(Choice::Text(self.text().map(str::to_string)));
+            for topic in topics {
+                options.push(Choice::Filter {
+                    topic: topic.clone(),
+                    label: self.backend().label(topic).to_string(),
+                    filter: self.parameters().topics.get(topic).cloned().flatten(),
+                });
             }
 
-            let search_parameter: &SearchParameter = self.parameters();
-            self.tree()
-                .search(search_parameter)
-                .iter()
-                .for_each(|project| options.push(project.source.clone()));
-
-            let selected = Select::new("Select a project or Filter Search", options)
-                .prompt()
-                .unwrap();
-            let topic_name = selected
-                .split(": ")
-                .collect::<Vec<&str>>()
-                .into_iter()
-                .map(str::to_string)
-                .collect::<Vec<String>>();
-            if topic_name.len() > 1 {
-                let tree = self.tree();
-                let value: Option<String> =
-                    parameters_update(&topic_name[0], tree, search_parameter.clone());
-                self.parameters_mut()
-                    .topics
-                    .insert(topic_name[0].to_string(), value);
-                continue;
-            }
+            options.extend(found.into_iter().map(Choice::Project));
 
-            return Ok(selected);
+            let selected: Choice =
+                Select::new("Select a project or Filter Search", options).prompt()?;
+            match selected {
+                Choice::Filter { topic, .. } => {
+                    let filter: Option<Filter> =
+                        parameters_update(&topic, self.backend(), self.parameters())?;
+                    self.parameters_mut().topics.insert(topic, filter);
+                }
+                Choice::Text(text) => {
+                    let text: String = Text::new("Words to look for, empty to clear: ")
+                        .with_initial_value(text.as_deref().unwrap_or_default())
+                        .prompt()?;
+                    let text: &str = text.trim();
+                    *self.text_mut() = (!text.is_empty()).then(|| text.to_string());
+                }
+                Choice::Project(project) => return Ok(project.source.clone()),
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selected_filters_are_checked_again() {
+        let options: Vec<TopicOption> = ["C|C++", "!important", "Rust", "(unspecified)"]
+            .into_iter()
+            .map(|value| TopicOption {
+                value: value.to_string(),
+                count: 1,
+            })
+            .collect();
+        // As read back from the command line, where the values are escaped
+        let Some(Filter::AnyOf(selected)) =
+            Filter::parse(r"C\|C++|\!important|rust|(unspecified)").unwrap()
+        else {
+            panic!("expected several values");
+        };
+        assert_eq!(checked_by_default(&options, &selected), vec![0, 1, 2, 3]);
+        assert!(checked_by_default(&options, &[Filter::NotApplicable]).is_empty());
+    }
+}
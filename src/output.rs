@@ -0,0 +1,329 @@
+use std::collections::BTreeSet;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    catalog::{Filter, Metadata, Project, TopicOption},
+    error::{Error, ParseError},
+    render::{Variable, VariableKind},
+};
+
+/// Marks a topic a project doesn't have in tables
+const MISSING: &str = "-";
+
+/// How command results are printed.
+///
+/// `json` and `toml` have the same layout: lists of projects are wrapped as `{ projects = [...] }`
+/// with each project laid out like a catalog entry, so a `toml` list is itself a valid catalog.
+/// Topics are wrapped as `{ topics = [{ topic, options = [{ value, count }] }] }` and the problems
+/// found by `validate` as `{ problems = [{ path, line, column, message }] }`. `config show` prints
+/// `{ settings = [{ key, value, source }] }`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Just the values, one per line
+    #[default]
+    Plain,
+    /// Aligned columns with a header
+    Table,
+    Json,
+    Toml,
+}
+
+/// Every value found for a topic, with how many projects have it
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct TopicOptions {
+    pub topic: String,
+    pub options: Vec<TopicOption>,
+}
+
+impl TopicOptions {
+    /// The options as `value (count)`, separated by commas
+    fn joined(&self) -> String {
+        self.options
+            .iter()
+            .map(TopicOption::to_string)
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// The effective value of a setting and where it comes from
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct SettingEntry {
+    pub key: String,
+    pub value: toml::Value,
+    pub source: String,
+}
+
+#[derive(Serialize)]
+struct ProjectList<'a> {
+    projects: &'a [&'a Project],
+}
+
+#[derive(Serialize)]
+struct ProblemList<'a> {
+    problems: &'a [ParseError],
+}
+
+#[derive(Serialize)]
+struct SettingList<'a> {
+    settings: &'a [SettingEntry],
+}
+
+#[derive(Serialize)]
+struct TopicList<'a> {
+    topics: &'a [TopicOptions],
+}
+
+fn json<T: Serialize>(value: &T) -> Result<String, Error> {
+    let output: String =
+        serde_json::to_string_pretty(value).map_err(|err| Error::Serialize(err.to_string()))?;
+    Ok(output + "\n")
+}
+
+fn toml<T: Serialize>(value: &T) -> Result<String, Error> {
+    toml::to_string(value).map_err(|err| Error::Serialize(err.to_string()))
+}
+
+/// Pads every column to its widest cell
+fn table(header: Vec<String>, rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut output: String = String::new();
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        output.push_str(line.join("  ").trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+/// Projects as returned by a search, in order
+pub fn projects(format: Format, projects: &[&Project]) -> Result<String, Error> {
+    match format {
+        Format::Plain => Ok(projects
+            .iter()
+            .map(|project| format!("{}\n", project.source))
+            .collect()),
+        Format::Table => {
+            let topics: BTreeSet<&String> = projects
+                .iter()
+                .flat_map(|project| project.topics.keys())
+                .collect();
+            let mut header: Vec<String> = vec!["SOURCE".to_string()];
+            header.extend(topics.iter().map(|topic| topic.to_uppercase()));
+            let rows: Vec<Vec<String>> = projects
+                .iter()
+                .map(|project| {
+                    let mut row: Vec<String> = vec![project.source.clone()];
+                    row.extend(topics.iter().map(|topic| {
+                        project
+                            .topics
+                            .get(*topic)
+                            .map(|values| joined(values))
+                            .unwrap_or_else(|| MISSING.to_string())
+                    }));
+                    row
+                })
+                .collect();
+            Ok(table(header, rows))
+        }
+        Format::Json => json(&ProjectList { projects }),
+        Format::Toml => toml(&ProjectList { projects }),
+    }
+}
+
+/// The values of a topic separated by commas, `(not applicable)` if there is none
+fn joined(values: &[String]) -> String {
+    match values {
+        [] => Filter::NOT_APPLICABLE.to_string(),
+        values => values.join(", "),
+    }
+}
+
+/// Description of a variable for people, like `one of MIT, Apache-2.0, default "MIT"`
+fn describe(variable: &Variable) -> String {
+    let kind: String = match &variable.kind {
+        VariableKind::Text { .. } => "text".to_string(),
+        VariableKind::Select { options, .. } => format!("one of {}", options.join(", ")),
+        VariableKind::Confirm { .. } => "true or false".to_string(),
+    };
+    match variable.default_value() {
+        None => kind,
+        Some(default) => format!("{kind}, default {default:?}"),
+    }
+}
+
+/// The metadata a project has, labelled and in a fixed order
+fn details(metadata: &Metadata) -> Vec<(&'static str, String)> {
+    let tags: Option<String> = (!metadata.tags.is_empty()).then(|| metadata.tags.join(", "));
+    [
+        ("name", metadata.name.clone()),
+        ("description", metadata.description.clone()),
+        ("maintainer", metadata.maintainer.clone()),
+        ("tags", tags),
+        ("license", metadata.license.clone()),
+        ("last updated", metadata.last_updated.clone()),
+    ]
+    .into_iter()
+    .filter_map(|(label, value)| Some((label, value?)))
+    .collect()
+}
+
+/// All the details of a single project
+pub fn project(format: Format, project: &Project) -> Result<String, Error> {
+    let details: Vec<(&str, String)> = details(&project.metadata);
+    let mut topics: Vec<(&String, String)> = project
+        .topics
+        .iter()
+        .map(|(topic, values)| (topic, joined(values)))
+        .collect();
+    topics.sort();
+    match format {
+        Format::Plain => {
+            let mut output: String = format!("source: {}\n", project.source);
+            if let Some(origin) = &project.origin {
+                output.push_str(&format!("catalog: {}\n", origin.display()));
+            }
+            for (label, value) in details {
+                output.push_str(&format!("{label}: {value}\n"));
+            }
+            if !topics.is_empty() {
+                output.push_str("topics:\n");
+                for (topic, value) in topics {
+                    output.push_str(&format!("  {topic}: {value}\n"));
+                }
+            }
+            if !project.variables.is_empty() {
+                output.push_str("variables:\n");
+                for variable in &project.variables {
+                    output.push_str(&format!("  {} ({})\n", variable.name, describe(variable)));
+                }
+            }
+            Ok(output)
+        }
+        Format::Table => {
+            let mut rows: Vec<Vec<String>> = vec![vec![
+                "source".to_string(),
+                String::new(),
+                project.source.clone(),
+            ]];
+            if let Some(origin) = &project.origin {
+                rows.push(vec![
+                    "catalog".to_string(),
+                    String::new(),
+                    origin.display().to_string(),
+                ]);
+            }
+            rows.extend(
+                details
+                    .into_iter()
+                    .map(|(label, value)| vec![label.to_string(), String::new(), value]),
+            );
+            rows.extend(
+                topics
+                    .into_iter()
+                    .map(|(topic, value)| vec!["topic".to_string(), topic.clone(), value]),
+            );
+            rows.extend(project.variables.iter().map(|variable| {
+                vec![
+                    "variable".to_string(),
+                    variable.name.clone(),
+                    describe(variable),
+                ]
+            }));
+            let header: Vec<String> = vec!["FIELD".into(), "NAME".into(), "VALUE".into()];
+            Ok(table(header, rows))
+        }
+        Format::Json => json(project),
+        Format::Toml => toml(project),
+    }
+}
+
+/// The options of each topic with their counts, in the given order
+pub fn topics(format: Format, topics: &[TopicOptions]) -> Result<String, Error> {
+    match format {
+        Format::Plain => Ok(topics
+            .iter()
+            .map(|topic| format!("{}: {}\n", topic.topic, topic.joined()))
+            .collect()),
+        Format::Table => {
+            let header: Vec<String> = vec!["TOPIC".to_string(), "OPTIONS".to_string()];
+            let rows: Vec<Vec<String>> = topics
+                .iter()
+                .map(|topic| vec![topic.topic.clone(), topic.joined()])
+                .collect();
+            Ok(table(header, rows))
+        }
+        Format::Json => json(&TopicList { topics }),
+        Format::Toml => toml(&TopicList { topics }),
+    }
+}
+
+/// Problems found in a catalog, each with its location
+pub fn problems(format: Format, problems: &[ParseError]) -> Result<String, Error> {
+    match format {
+        Format::Plain => Ok(problems
+            .iter()
+            .map(|problem| format!("{problem}\n"))
+            .collect()),
+        Format::Table => {
+            let header: Vec<String> = vec![
+                "FILE".into(),
+                "LINE".into(),
+                "COLUMN".into(),
+                "PROBLEM".into(),
+            ];
+            let rows: Vec<Vec<String>> = problems
+                .iter()
+                .map(|problem| {
+                    vec![
+                        problem.path.display().to_string(),
+                        problem.line.to_string(),
+                        problem.column.to_string(),
+                        problem.message.clone(),
+                    ]
+                })
+                .collect();
+            Ok(table(header, rows))
+        }
+        Format::Json => json(&ProblemList { problems }),
+        Format::Toml => toml(&ProblemList { problems }),
+    }
+}
+
+/// Settings with their value written as in a configuration file
+pub fn settings(format: Format, settings: &[SettingEntry]) -> Result<String, Error> {
+    match format {
+        Format::Plain => Ok(settings
+            .iter()
+            .map(|setting| format!("{} = {} ({})\n", setting.key, setting.value, setting.source))
+            .collect()),
+        Format::Table => {
+            let header: Vec<String> = vec!["KEY".into(), "VALUE".into(), "SOURCE".into()];
+            let rows: Vec<Vec<String>> = settings
+                .iter()
+                .map(|setting| {
+                    vec![
+                        setting.key.clone(),
+                        setting.value.to_string(),
+                        setting.source.clone(),
+                    ]
+                })
+                .collect();
+            Ok(table(header, rows))
+        }
+        Format::Json => json(&SettingList { settings }),
+        Format::Toml => toml(&SettingList { settings }),
+    }
+}
@@ -1,208 +1,1145 @@
-use std::collections::{HashMap, HashSet};
-use std::sync::mpsc::Iter;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::UNIX_EPOCH,
+};
 
-use clap::builder::Str;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Serialize};
 use sqlite::Connection;
-use sqlite::Error;
-use sqlite::Value;
+use sqlite::State;
+use sqlite::Statement;
 
-use crate::projects::SearchParameter;
+use crate::{
+    catalog::{
+        self, Aliases, CatalogBackend, CatalogFiles, Filter, Project, Projects, SearchParameter,
+        Topic, TopicOption,
+    },
+    error::Error,
+    manifest,
+};
 
-#[derive(Debug, Default, Deserialize)]
-pub struct Projects {
-    pub projects: Vec<Project>,
+/// Bumped whenever `SCHEMA` or the layout of the stored documents changes, databases with another
+/// version are rebuilt
+const SCHEMA_VERSION: i64 = 9;
+
+/// Topic names and values are only ever bound as parameters, never written into a query.
+/// `projects` keeps each whole entry as JSON in `document` with the file it came from in
+/// `origin`, `topics` holds one row per topic of each project for searching (several for a topic
+/// with many values), and `not_applicable` one row per topic a project has an empty array for.
+/// `text`, created apart by `TEXT_SCHEMA`, is the full-text index of each project. `aliases`
+/// keeps the aliases of each topic as JSON, from canonical value to other spellings, and
+/// `declared` the declaration of each topic of the `[topics]` section as JSON. `catalog` has a row
+/// for each file and directory the data was imported from: catalogs, local templates and their
+/// manifests. `requested` lists the catalogs given to `Data::open`, in order.
+const SCHEMA: &str = r#"
+    DROP TABLE IF EXISTS "declared";
+    DROP TABLE IF EXISTS "aliases";
+    DROP TABLE IF EXISTS "text";
+    DROP TABLE IF EXISTS "not_applicable";
+    DROP TABLE IF EXISTS "topics";
+    DROP TABLE IF EXISTS "projects";
+    DROP TABLE IF EXISTS "catalog";
+    DROP TABLE IF EXISTS "requested";
+    CREATE TABLE "projects" (
+        "id" INTEGER PRIMARY KEY,
+        "source" TEXT NOT NULL UNIQUE,
+        "document" TEXT NOT NULL,
+        "origin" TEXT
+    );
+    CREATE TABLE "topics" (
+        "project" INTEGER NOT NULL REFERENCES "projects" ("id"),
+        "topic" TEXT NOT NULL,
+        "value" TEXT NOT NULL,
+        PRIMARY KEY ("project", "topic", "value")
+    );
+    CREATE INDEX "topics_by_value" ON "topics" ("topic", "value" COLLATE NOCASE);
+    CREATE TABLE "not_applicable" (
+        "project" INTEGER NOT NULL REFERENCES "projects" ("id"),
+        "topic" TEXT NOT NULL,
+        PRIMARY KEY ("project", "topic")
+    );
+    CREATE TABLE "aliases" (
+        "topic" TEXT PRIMARY KEY,
+        "document" TEXT NOT NULL
+    );
+    CREATE TABLE "declared" (
+        "topic" TEXT PRIMARY KEY,
+        "document" TEXT NOT NULL
+    );
+    CREATE TABLE "catalog" (
+        "path" TEXT PRIMARY KEY,
+        "modified" INTEGER NOT NULL,
+        "size" INTEGER NOT NULL,
+        "hash" TEXT NOT NULL
+    );
+    CREATE TABLE "requested" (
+        "position" INTEGER PRIMARY KEY,
+        "path" TEXT NOT NULL
+    );
+"#;
+
+/// Full-text index of each project, its rowid is the id of the project. Only created when SQLite
+/// was built with FTS5, which takes `SQLITE_ENABLE_FTS5` set while building, see `has_text`
+const TEXT_SCHEMA: &str = r#"
+    CREATE VIRTUAL TABLE "text" USING fts5(
+        "source", "name", "description", "tags", "topics"
+    );
+"#;
+
+/// Weight of each column of `text` in the ranking, a match in the name counts the most
+const TEXT_WEIGHTS: &str = r#"bm25("text", 1.0, 10.0, 4.0, 6.0, 3.0)"#;
+
+/// Condition on a project row aliased `p` having a topic (bound first) equal to a value (bound
+/// second), ignoring ASCII case like `SearchTree::search`
+const HAS_TOPIC: &str = r#"EXISTS (
+    SELECT 1 FROM "topics" "f"
+    WHERE "f"."project" = "p"."id" AND "f"."topic" = ? AND "f"."value" = ? COLLATE NOCASE
+)"#;
+
+/// Condition on a project row aliased `p` having an empty array for a topic (bound once)
+const IS_NOT_APPLICABLE: &str = r#"EXISTS (
+    SELECT 1 FROM "not_applicable" "n" WHERE "n"."project" = "p"."id" AND "n"."topic" = ?
+)"#;
+
+/// Condition on a project row aliased `p` not mentioning a topic (bound twice)
+const IS_UNSPECIFIED: &str = r#"NOT EXISTS (
+    SELECT 1 FROM "topics" "f" WHERE "f"."project" = "p"."id" AND "f"."topic" = ?
+) AND NOT EXISTS (
+    SELECT 1 FROM "not_applicable" "n" WHERE "n"."project" = "p"."id" AND "n"."topic" = ?
+)"#;
+
+/// What identifies a version of a file or directory read by an import, compared to skip imports
+#[derive(Debug, PartialEq, Eq)]
+struct Fingerprint {
+    /// Canonical path
+    path: PathBuf,
+    /// Nanoseconds since the epoch
+    modified: i64,
+    size: i64,
+    /// Hash of the content, only computed when the rest changed
+    hash: Option<String>,
 }
 
+impl Fingerprint {
+    fn of(catalog: &Path) -> Result<Self, Error> {
+        let path: PathBuf = fs::canonicalize(catalog).map_err(|err| Error::io(catalog, err))?;
+        let metadata = fs::metadata(&path).map_err(|err| Error::io(catalog, err))?;
+        let modified: i64 = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |modified| modified.as_nanos() as i64);
+        Ok(Self {
+            path,
+            modified,
+            size: metadata.len() as i64,
+            hash: None,
+        })
+    }
+
+    /// Fingerprint with the hash of the content
+    fn hashed(catalog: &Path) -> Result<Self, Error> {
+        let mut fingerprint: Self = Self::of(catalog)?;
+        fingerprint.hash = Some(hash(&content(&fingerprint.path)?));
+        Ok(fingerprint)
+    }
+
+    /// Whether the file or directory is still the same, comparing the content only if the
+    /// modification time or size changed. `None` if it is gone.
+    fn check(&self) -> Option<Self> {
+        let current: Self = Self::of(&self.path).ok()?;
+        if current.modified == self.modified && current.size == self.size {
+            return Some(Self {
+                hash: self.hash.clone(),
+                ..current
+            });
+        }
+        let current: Self = Self::hashed(&self.path).ok()?;
+        (current.hash == self.hash).then_some(current)
+    }
+}
+
+/// Content of a catalog file, or the names of the entries of a directory
+fn content(path: &Path) -> Result<String, Error> {
+    if !path.is_dir() {
+        return fs::read_to_string(path).map_err(|err| Error::io(path, err));
+    }
+    let mut names: Vec<String> = Vec::new();
+    for entry in fs::read_dir(path).map_err(|err| Error::io(path, err))? {
+        let entry = entry.map_err(|err| Error::io(path, err))?;
+        names.push(entry.file_name().to_string_lossy().into_owned());
+    }
+    names.sort();
+    Ok(names.join("\n"))
+}
+
+/// 64-bit FNV-1a hash of `data`, in hex. It names the database files, so unlike `DefaultHasher`
+/// it must not change between Rust releases.
+fn hash(data: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in data.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
+
+/// Catalog searched in SQLite, in memory with `Data::new` or kept on disk with `Data::open`
 pub struct Data {
     connection: Connection,
     topics: Vec<String>,
+    /// Applied to the filters, the stored projects are already canonical
+    aliases: Aliases,
+    /// The `[topics]` section of the catalog
+    declared: BTreeMap<String, Topic>,
+    /// Whether the full-text index is there, see `has_text`
+    text: bool,
 }
 
 impl Data {
-    pub fn new(projects: Vec<Project>) -> Self {
-        let mut topics: HashSet<String> = HashSet::new();
-        // println!("Starting connection...");
-        let connection: Connection = sqlite::open(":memory:").unwrap();
-        // println!("Checking projects...");
-        for project in projects.iter() {
-            for key in project.topics.keys() {
-                if !topics.contains(key) {
-                    topics.insert(key.clone());
-                }
-            }
+    /// Catalog kept in memory, built from `catalog` which must already be canonical
+    pub fn new(catalog: &Projects) -> Result<Self, Error> {
+        let connection: Connection = sqlite::open(":memory:")?;
+        create(&connection)?;
+        import(&connection, catalog)?;
+        Self::with_connection(connection)
+    }
+
+    /// Default location of the database for a catalog file, one per catalog under the user's data
+    /// directory
+    pub fn default_path(catalogs: &[PathBuf]) -> Result<PathBuf, Error> {
+        let directory: PathBuf = dirs::data_dir()
+            .ok_or(Error::NoDataDirectory)?
+            .join(env!("CARGO_PKG_NAME"));
+        let mut names: Vec<String> = Vec::with_capacity(catalogs.len());
+        for catalog in catalogs {
+            let catalog: PathBuf =
+                fs::canonicalize(catalog).map_err(|err| Error::io(catalog, err))?;
+            names.push(catalog.to_string_lossy().into_owned());
+        }
+        Ok(directory.join(format!("{}.sqlite", hash(&names.join("\n")))))
+    }
+
+    /// Catalogs kept in the database file at `database`, created if missing.
+    ///
+    /// The catalogs are only read and imported again when they aren't the ones of the last
+    /// import, or when a file or directory it read changed, the manifests of local templates
+    /// included: its modification time and size are checked first, then the hash of its content.
+    pub fn open(database: &Path, catalogs: &[PathBuf]) -> Result<Self, Error> {
+        if let Some(directory) = database.parent() {
+            fs::create_dir_all(directory).map_err(|err| Error::io(directory, err))?;
         }
-        let mut topics: Vec<String> = topics.into_iter().collect::<Vec<String>>();
-        // println!("Found these unique keys: {topics:?}\n");
-        let mut keys = topics
+        let connection: Connection = sqlite::open(database)?;
+        if user_version(&connection)? != SCHEMA_VERSION {
+            create(&connection)?;
+            connection.execute(format!("PRAGMA user_version = {SCHEMA_VERSION};"))?;
+        }
+
+        let requested: Vec<PathBuf> = catalogs
             .iter()
-            .to_owned()
-            .map(|key| format!("{key} TEXT"))
-            .collect::<Vec<String>>()
-            .join(", ");
-        if !keys.is_empty() {
-            keys = format!(", {keys}");
-        }
-        let query = format!("CREATE TABLE projects (source TEXT PRIMARY KEY{keys});");
-        // println!("Running Query:\n{query}\n");
-        connection.execute(query).unwrap();
-        // println!("Query executed successfully");
-
-        let mut keys = topics.to_vec().join(", ");
-        if !keys.is_empty() {
-            keys = format!(", {keys}");
-        }
-        for project in projects {
-            let mut keys = "source".to_string();
-            let mut values = format!("'{}'", project.source);
-            for topic in topics.iter() {
-                match project.topics.get(topic) {
-                    None => {}
-                    Some(value) => {
-                        keys = format!("{keys}, {topic}");
-                        values = format!("{values}, '{value}'");
-                    }
-                }
-            }
-            let query = format!("INSERT INTO projects ({keys}) VALUES ({values});");
-
-            // println!("Running: \n{query}\n");
-            connection.execute(query).unwrap();
-        }
-
-        // let query = "SELECT * FROM projects;";
-        // topics.insert(0, "source".to_string());
-        // for row in connection
-        //     .prepare(query)
-        //     .unwrap()
-        //     .into_iter()
-        //     .map(|row| row.unwrap())
-        // {
-        //     for (index, topic) in topics.iter().enumerate() {
-        //         if row.contains(index) {
-        //             let value: Result<&str, Error> = row.try_read::<&str, _>(index);
-        //             if let Ok(value) = value {
-        //                 println!("{topic}: {}", value);
-        //             }
-        //         }
-        //     }
-        //     println!("\n-------------------------------------\n");
-        // }
-        let output = Self { connection, topics };
-        // println!("Retrieving topics for deployment: ");
-        // let result = output.retrieve_topics(Default::default(), "deployment");
-        // println!("Output: {result:?}");
-        // println!("Retrieving topics for platform: ");
-        // let result = output.retrieve_topics(Default::default(), "platform");
-        // println!("Output: {result:?}");
-        // println!("Retrieving topics for language: ");
-        // let result = output.retrieve_topics(Default::default(), "language");
-        // println!("Output: {result:?}");
-        // println!("Retrieving topics for database: ");
-        // let result = output.retrieve_topics(Default::default(), "database");
-        // println!("Output: {result:?}");
-
-        println!("Finished");
-        let projects = output.search(&Default::default());
-        println!("Projects is:\n{projects:#?}");
-        output
-    }
-
-    fn retrieve_topics(
-        &self,
-        parameters: &HashMap<String, String>,
-        target_topic: &str,
-    ) -> Vec<String> {
-        let topics = &self.topics;
-        let mut sections = vec![format!("{} IS NOT NULL", target_topic.to_string())];
-        for topic in topics {
-            if parameters.is_empty() {
-                // println!("Empty");
-                break;
-            }
-            // println!("topic: {topic}");
-            if parameters.contains_key(topic) {
-                if topic.eq(target_topic) {
-                    // println!("Target");
-                } else {
-                    // println!("Not Target");
-                    let value = parameters.get(topic).unwrap();
-                    let section = format!("{topic}={value}");
-                    sections.push(section);
-                };
+            .map(|catalog| fs::canonicalize(catalog).map_err(|err| Error::io(catalog, err)))
+            .collect::<Result<_, _>>()?;
+        let stored: Vec<Fingerprint> = stored_fingerprints(&connection)?;
+        let same_catalogs: bool = stored_requested(&connection)? == requested;
+        let current: Option<Vec<Fingerprint>> = match stored.is_empty() || !same_catalogs {
+            true => None,
+            false => stored.iter().map(Fingerprint::check).collect(),
+        };
+        if current.as_ref() == Some(&stored) {
+            return Self::with_connection(connection);
+        }
+
+        connection.execute("BEGIN;")?;
+        let current: Vec<Fingerprint> = match current {
+            // Only the modification times changed
+            Some(current) => current,
+            None => {
+                let files: CatalogFiles = Projects::read_all(&requested)?;
+                let mut paths: Vec<PathBuf> =
+                    files.paths().into_iter().map(Path::to_path_buf).collect();
+                let mut parsed = Projects::merge_all(files)?;
+                paths.extend(manifest::merge_local(&mut parsed.projects)?);
+                parsed.canonicalize();
+                import(&connection, &parsed)?;
+                store_requested(&connection, &requested)?;
+                fingerprints(&paths)?
             }
-            // return topics.clone();
-        }
-        let sections = sections.join("\n    AND ");
-        // let inner_query = format!("    SELECT * FROM projects\n    WHERE {sections}");
-        // let full_query =
-        //     format!("SELECT DISTINCT {target_topic}\nFROM (\n{inner_query}\n) subquery");
-        let full_query = format!("SELECT DISTINCT {target_topic} FROM projects\n WHERE {sections}");
-        // println!("Running: {full_query}");
-        let mut output = Vec::with_capacity(topics.len());
-        for row in self
+        };
+        store_fingerprints(&connection, &current)?;
+        connection.execute("COMMIT;")?;
+        Self::with_connection(connection)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, Error> {
+        let mut topics: Vec<String> = Vec::new();
+        let statement = connection.prepare(
+            r#"SELECT "topic" FROM "topics" UNION SELECT "topic" FROM "not_applicable";"#,
+        )?;
+        for row in statement.into_iter() {
+            topics.push(row?.try_read::<&str, _>(0)?.to_string());
+        }
+        let aliases: Aliases = Aliases(documents_by_topic(&connection, "aliases")?);
+        let declared: BTreeMap<String, Topic> = documents_by_topic(&connection, "declared")?;
+        let text: bool = has_text(&connection)?;
+        Ok(Self {
+            connection,
+            topics: catalog::order_topics(topics, &declared),
+            aliases,
+            declared,
+            text,
+        })
+    }
+
+    /// The known spelling closest to `value` for `topic`, if there is one close enough
+    fn suggest(&self, topic: &str, value: &str) -> Result<Option<String>, Error> {
+        let statement = self
             .connection
-            .prepare(full_query)
-            .unwrap()
+            .prepare(r#"SELECT DISTINCT "value" FROM "topics" WHERE "topic" = ?;"#)?
             .into_iter()
-            .map(|row| row.unwrap())
-        {
-            // Query will return single column rows, this probably isn't necessary
-            for (index, _topic) in topics.iter().enumerate() {
-                if row.contains(index) {
-                    let value: Result<&str, Error> = row.try_read::<&str, _>(index);
-                    // print!("{value} ");
-                    if let Ok(value) = value {
-                        // println!("{topic}: {}", value);
-                        output.push(value.to_string());
-                    }
+            .bind((1, topic))?;
+        let mut candidates: Vec<String> = Vec::new();
+        for row in statement {
+            candidates.push(row?.try_read::<&str, _>(0)?.to_string());
+        }
+        let candidates = candidates.iter().map(String::as_str);
+        Ok(catalog::suggest(
+            value,
+            self.aliases.spellings(topic).into_iter().chain(candidates),
+        ))
+    }
+
+    /// Every filter must name a known topic and only values that some project has
+    fn validate(&self, parameter: &SearchParameter) -> Result<(), Error> {
+        let mut statement = self.connection.prepare(
+            r#"SELECT 1 FROM "topics" WHERE "topic" = ? AND "value" = ? COLLATE NOCASE LIMIT 1;"#,
+        )?;
+        for (topic, filter) in parameter.topics.iter() {
+            if !self.topics.contains(topic) {
+                return Err(Error::MissingTopic(topic.to_string()));
+            }
+            for value in filter.iter().flat_map(Filter::values) {
+                statement.reset()?;
+                statement.bind((1, topic.as_str()))?;
+                statement.bind((2, value))?;
+                if statement.next()? == State::Done {
+                    return Err(Error::UnknownFilterValue(
+                        topic.to_string(),
+                        value.to_string(),
+                        self.suggest(topic, value)?,
+                    ));
                 }
             }
-            // println!("-----------------------------");
         }
+        Ok(())
+    }
 
-        output
+    /// Conditions on the project row `p` for the filters in `parameter`, skipping `except`, with
+    /// the topics and values to bind in order
+    fn conditions<'a>(
+        &self,
+        parameter: &'a SearchParameter,
+        except: Option<&str>,
+    ) -> (Vec<String>, Vec<&'a str>) {
+        let mut conditions: Vec<String> = Vec::new();
+        let mut values: Vec<&str> = Vec::new();
+        // Topic order, so the same parameter always prepares the same query
+        for topic in &self.topics {
+            if Some(topic.as_str()) == except {
+                continue;
+            }
+            let Some((topic, Some(filter))) = parameter.topics.get_key_value(topic) else {
+                continue;
+            };
+            conditions.push(condition(topic, filter, &mut values));
+        }
+        (conditions, values)
     }
+}
 
-    fn search(&self, parameters: &HashMap<String, String>) -> Vec<Project> {
-        let query: &str = "SELECT * FROM projects;";
-        let mut projects: Vec<Project> = Vec::new();
-        for row in self
-            .connection
-            .prepare(query)
-            .unwrap()
-            .into_iter()
-            .map(|row| row.unwrap())
+/// Condition on the project row `p` matching `filter` on `topic`, pushing what to bind to
+/// `values` in order
+fn condition<'a>(topic: &'a str, filter: &'a Filter, values: &mut Vec<&'a str>) -> String {
+    let alternatives = |filters: &'a [Filter], values: &mut Vec<&'a str>| -> String {
+        let conditions: Vec<String> = filters
+            .iter()
+            .map(|filter| condition(topic, filter, values))
+            .collect();
+        format!("({})", conditions.join(" OR "))
+    };
+    match filter {
+        Filter::Value(value) => {
+            values.extend([topic, value.as_str()]);
+            HAS_TOPIC.to_string()
+        }
+        Filter::Unspecified => {
+            values.extend([topic, topic]);
+            format!("({IS_UNSPECIFIED})")
+        }
+        Filter::NotApplicable => {
+            values.push(topic);
+            IS_NOT_APPLICABLE.to_string()
+        }
+        Filter::AnyOf(filters) => alternatives(filters, values),
+        Filter::NoneOf(filters) => format!("NOT {}", alternatives(filters, values)),
+    }
+}
+
+impl CatalogBackend for Data {
+    fn get_topics(&self) -> &[String] {
+        &self.topics
+    }
+
+    fn get_aliases(&self) -> &Aliases {
+        &self.aliases
+    }
+
+    fn get_declared(&self) -> &BTreeMap<String, Topic> {
+        &self.declared
+    }
+
+    fn get_project(&self, source: &str) -> Result<Option<Arc<Project>>, Error> {
+        let mut statement = self.connection.prepare(
+            r#"SELECT "p"."document", "p"."origin" FROM "projects" "p" WHERE "p"."source" = ?;"#,
+        )?;
+        statement.bind((1, source))?;
+        Ok(documents(statement)?.into_iter().next())
+    }
+
+    /// Every value of `target_topic` among the projects matching the other filters of
+    /// `parameter`, with how many have it
+    fn retrieve_topic_options(
+        &self,
+        target_topic: &str,
+        parameter: &SearchParameter,
+    ) -> Result<Vec<TopicOption>, Error> {
+        if !self.topics.iter().any(|topic| topic == target_topic) {
+            return Err(Error::MissingTopic(target_topic.to_string()));
+        }
+        let parameter: &SearchParameter = &self.aliases.parameter(parameter);
+        self.validate(parameter)?;
+        let (mut conditions, values) = self.conditions(parameter, Some(target_topic));
+        conditions.insert(0, "1".to_string());
+        let conditions: String = conditions.join(" AND ");
+        // One option for the spellings differing in case, like `SearchTree`
+        let query = format!(
+            r#"SELECT MIN("t"."value"), COUNT(DISTINCT "t"."project") FROM "topics" "t"
+            WHERE "t"."topic" = ? AND "t"."project" IN (
+                SELECT "p"."id" FROM "projects" "p" WHERE {conditions}
+            )
+            GROUP BY "t"."value" COLLATE NOCASE;"#
+        );
+        let mut statement = self.connection.prepare(query)?;
+        statement.bind((1, target_topic))?;
+        for (index, value) in values.iter().enumerate() {
+            statement.bind((index + 2, *value))?;
+        }
+        let mut output: Vec<TopicOption> = Vec::new();
+        for row in statement.into_iter() {
+            let row = row?;
+            output.push(TopicOption {
+                value: row.try_read::<&str, _>(0)?.to_string(),
+                count: row.try_read::<i64, _>(1)? as usize,
+            });
+        }
+        let mut output: Vec<TopicOption> =
+            catalog::order_values(output, self.declared.get(target_topic));
+
+        let query = format!(
+            r#"SELECT COALESCE(SUM({IS_UNSPECIFIED}), 0), COALESCE(SUM({IS_NOT_APPLICABLE}), 0)
+            FROM "projects" "p" WHERE {conditions};"#
+        );
+        let mut statement = self.connection.prepare(query)?;
+        for index in 1..=3 {
+            statement.bind((index, target_topic))?;
+        }
+        for (index, value) in values.iter().enumerate() {
+            statement.bind((index + 4, *value))?;
+        }
+        if statement.next()? == State::Row {
+            output.extend(TopicOption::without_value(
+                statement.read::<i64, _>(0)? as usize,
+                statement.read::<i64, _>(1)? as usize,
+            ));
+        }
+        Ok(output)
+    }
+
+    fn search_text(
+        &self,
+        parameter: &SearchParameter,
+        text: &str,
+    ) -> Result<Vec<Arc<Project>>, Error> {
+        let Some(query) = text_query(text) else {
+            return self.search(parameter);
+        };
+        if !self.text {
+            return Err(Error::NoTextSearch);
+        }
+        let parameter: &SearchParameter = &self.aliases.parameter(parameter);
+        self.validate(parameter)?;
+        let (mut conditions, values) = self.conditions(parameter, None);
+        conditions.insert(0, r#""text" MATCH ?"#.to_string());
+        let query_sql = format!(
+            r#"SELECT "p"."document", "p"."origin" FROM "text"
+            JOIN "projects" "p" ON "p"."id" = "text"."rowid"
+            WHERE {}
+            ORDER BY {TEXT_WEIGHTS}, "p"."id";"#,
+            conditions.join(" AND ")
+        );
+        let mut statement = self.connection.prepare(query_sql)?;
+        statement.bind((1, query.as_str()))?;
+        for (index, value) in values.into_iter().enumerate() {
+            statement.bind((index + 2, value))?;
+        }
+        documents(statement)
+    }
+
+    fn search(&self, parameter: &SearchParameter) -> Result<Vec<Arc<Project>>, Error> {
+        let parameter: &SearchParameter = &self.aliases.parameter(parameter);
+        self.validate(parameter)?;
+        let (mut conditions, values) = self.conditions(parameter, None);
+        conditions.insert(0, "1".to_string());
+        let query = format!(
+            r#"SELECT "p"."document", "p"."origin" FROM "projects" "p" WHERE {} ORDER BY "p"."id";"#,
+            conditions.join(" AND ")
+        );
+        let mut statement = self.connection.prepare(query)?;
+        for (index, value) in values.into_iter().enumerate() {
+            statement.bind((index + 1, value))?;
+        }
+        documents(statement)
+    }
+}
+
+/// Decodes the JSON documents of the table `table`, which has one per topic. `table` is one of
+/// the fixed names of `SCHEMA`.
+fn documents_by_topic<T: DeserializeOwned>(
+    connection: &Connection,
+    table: &str,
+) -> Result<BTreeMap<String, T>, Error> {
+    let mut documents: BTreeMap<String, T> = BTreeMap::new();
+    let statement = connection.prepare(format!(r#"SELECT "topic", "document" FROM "{table}";"#))?;
+    for row in statement.into_iter() {
+        let row = row?;
+        let document: T = serde_json::from_str(row.try_read::<&str, _>(1)?)
+            .map_err(|err| Error::CorruptDatabase(err.to_string()))?;
+        documents.insert(row.try_read::<&str, _>(0)?.to_string(), document);
+    }
+    Ok(documents)
+}
+
+/// Decodes the projects of a query selecting `document` then `origin`
+fn documents(statement: Statement) -> Result<Vec<Arc<Project>>, Error> {
+    let mut projects: Vec<Arc<Project>> = Vec::new();
+    for row in statement.into_iter() {
+        let row = row?;
+        let mut project: Project = serde_json::from_str(row.try_read::<&str, _>(0)?)
+            .map_err(|err| Error::CorruptDatabase(err.to_string()))?;
+        project.origin = row.try_read::<Option<&str>, _>(1)?.map(PathBuf::from);
+        projects.push(Arc::new(project));
+    }
+    Ok(projects)
+}
+
+/// FTS5 query matching any word of `text` as a prefix, `None` if it has no word. Only letters
+/// and digits are kept, so nothing typed can be read as FTS5 syntax.
+fn text_query(text: &str) -> Option<String> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\"*"))
+        .collect();
+    (!words.is_empty()).then(|| words.join(" OR "))
+}
+
+/// Creates the tables, dropping those of another version. `text` is left out when SQLite has no
+/// FTS5, so everything but `search_text` still works.
+fn create(connection: &Connection) -> Result<(), Error> {
+    connection.execute(SCHEMA)?;
+    let mut statement = connection.prepare("SELECT sqlite_compileoption_used('ENABLE_FTS5');")?;
+    statement.next()?;
+    if statement.read::<i64, _>(0)? == 1 {
+        connection.execute(TEXT_SCHEMA)?;
+    }
+    Ok(())
+}
+
+/// Whether the database has the `text` table and SQLite can read it. A database shared with a
+/// build that had FTS5 keeps the table, which is then left alone.
+fn has_text(connection: &Connection) -> Result<bool, Error> {
+    let mut statement = connection.prepare(
+        r#"SELECT sqlite_compileoption_used('ENABLE_FTS5')
+        AND EXISTS (SELECT 1 FROM "sqlite_master" WHERE "name" = 'text');"#,
+    )?;
+    statement.next()?;
+    Ok(statement.read::<i64, _>(0)? == 1)
+}
+
+fn user_version(connection: &Connection) -> Result<i64, Error> {
+    let mut statement = connection.prepare("PRAGMA user_version;")?;
+    statement.next()?;
+    Ok(statement.read::<i64, _>(0)?)
+}
+
+/// Fingerprints with the hash of `paths`, once for each canonical path
+fn fingerprints(paths: &[PathBuf]) -> Result<Vec<Fingerprint>, Error> {
+    let mut fingerprints: Vec<Fingerprint> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let fingerprint: Fingerprint = Fingerprint::hashed(path)?;
+        if !fingerprints
+            .iter()
+            .any(|other| other.path == fingerprint.path)
         {
-            // println!("Setting source for row:\n{row:?}");
-            let source = row.try_read::<&str, _>(0).unwrap().to_string();
-            // println!("Source is {source}");
-
-            let topics = HashMap::new();
-            let mut project: Project = Project { source, topics };
-            // println!("Iterating through columns");
-            for (index, topic) in self.topics.iter().enumerate() {
-                if row.contains(index + 1) {
-                    // println!("Checking column {} for topic {topic}", index + 1);
-                    let value: Result<&str, Error> = row.try_read::<&str, usize>(index + 1);
-                    if let Ok(value) = value {
-                        // println!("Value is {value}");
-                        project.topics.insert(topic.to_string(), value.to_string());
-                    }
-                } else {
-                    break;
-                }
+            fingerprints.push(fingerprint);
+        }
+    }
+    Ok(fingerprints)
+}
+
+fn stored_fingerprints(connection: &Connection) -> Result<Vec<Fingerprint>, Error> {
+    let statement =
+        connection.prepare(r#"SELECT "path", "modified", "size", "hash" FROM "catalog";"#)?;
+    let mut fingerprints: Vec<Fingerprint> = Vec::new();
+    for row in statement.into_iter() {
+        let row = row?;
+        fingerprints.push(Fingerprint {
+            path: PathBuf::from(row.try_read::<&str, _>(0)?),
+            modified: row.try_read::<i64, _>(1)?,
+            size: row.try_read::<i64, _>(2)?,
+            hash: Some(row.try_read::<&str, _>(3)?.to_string()),
+        });
+    }
+    Ok(fingerprints)
+}
+
+fn store_fingerprints(connection: &Connection, fingerprints: &[Fingerprint]) -> Result<(), Error> {
+    connection.execute(r#"DELETE FROM "catalog";"#)?;
+    let mut statement = connection.prepare(
+        r#"INSERT INTO "catalog" ("path", "modified", "size", "hash") VALUES (?, ?, ?, ?);"#,
+    )?;
+    for fingerprint in fingerprints {
+        statement.reset()?;
+        statement.bind((1, fingerprint.path.to_string_lossy().as_ref()))?;
+        statement.bind((2, fingerprint.modified))?;
+        statement.bind((3, fingerprint.size))?;
+        statement.bind((4, fingerprint.hash.as_deref().unwrap_or_default()))?;
+        statement.next()?;
+    }
+    Ok(())
+}
+
+/// Catalogs of the last import, in order
+fn stored_requested(connection: &Connection) -> Result<Vec<PathBuf>, Error> {
+    let statement = connection.prepare(r#"SELECT "path" FROM "requested" ORDER BY "position";"#)?;
+    let mut requested: Vec<PathBuf> = Vec::new();
+    for row in statement.into_iter() {
+        requested.push(PathBuf::from(row?.try_read::<&str, _>(0)?));
+    }
+    Ok(requested)
+}
+
+fn store_requested(connection: &Connection, requested: &[PathBuf]) -> Result<(), Error> {
+    connection.execute(r#"DELETE FROM "requested";"#)?;
+    let mut statement =
+        connection.prepare(r#"INSERT INTO "requested" ("position", "path") VALUES (?, ?);"#)?;
+    for (position, path) in requested.iter().enumerate() {
+        statement.reset()?;
+        statement.bind((1, position as i64))?;
+        statement.bind((2, path.to_string_lossy().as_ref()))?;
+        statement.next()?;
+    }
+    Ok(())
+}
+
+/// Stores each document of `documents` as JSON in the table `table`, one of the fixed names of
+/// `SCHEMA`
+fn insert_by_topic<T: Serialize>(
+    connection: &Connection,
+    table: &str,
+    documents: &BTreeMap<String, T>,
+) -> Result<(), Error> {
+    let mut statement = connection.prepare(format!(
+        r#"INSERT INTO "{table}" ("topic", "document") VALUES (?, ?);"#
+    ))?;
+    for (topic, document) in documents {
+        let document: String =
+            serde_json::to_string(document).map_err(|err| Error::Serialize(err.to_string()))?;
+        statement.reset()?;
+        statement.bind((1, topic.as_str()))?;
+        statement.bind((2, document.as_str()))?;
+        statement.next()?;
+    }
+    Ok(())
+}
+
+/// Replaces the projects, aliases and declared topics in the database with those of `catalog`
+fn import(connection: &Connection, catalog: &Projects) -> Result<(), Error> {
+    connection.execute(
+        r#"DELETE FROM "declared"; DELETE FROM "aliases"; DELETE FROM "not_applicable";
+        DELETE FROM "topics"; DELETE FROM "projects";"#,
+    )?;
+    let text: bool = has_text(connection)?;
+    if text {
+        connection.execute(r#"DELETE FROM "text";"#)?;
+    }
+    insert_by_topic(connection, "aliases", &catalog.aliases.0)?;
+    insert_by_topic(connection, "declared", &catalog.topics)?;
+    let mut insert_project = connection.prepare(
+        r#"INSERT INTO "projects" ("id", "source", "document", "origin") VALUES (?, ?, ?, ?);"#,
+    )?;
+    let mut insert_topic = connection
+        .prepare(r#"INSERT INTO "topics" ("project", "topic", "value") VALUES (?, ?, ?);"#)?;
+    let mut insert_not_applicable = connection
+        .prepare(r#"INSERT INTO "not_applicable" ("project", "topic") VALUES (?, ?);"#)?;
+    let mut insert_text = match text {
+        true => Some(connection.prepare(
+            r#"INSERT INTO "text" ("rowid", "source", "name", "description", "tags", "topics")
+            VALUES (?, ?, ?, ?, ?, ?);"#,
+        )?),
+        false => None,
+    };
+    for (id, project) in catalog.projects.iter().enumerate() {
+        // Ids follow the catalog order, which is the order of the results
+        let id: i64 = id as i64;
+        let document: String =
+            serde_json::to_string(project).map_err(|err| Error::Serialize(err.to_string()))?;
+        insert_project.reset()?;
+        insert_project.bind((1, id))?;
+        insert_project.bind((2, project.source.as_str()))?;
+        insert_project.bind((3, document.as_str()))?;
+        let origin: Option<String> = project
+            .origin
+            .as_ref()
+            .map(|origin| origin.to_string_lossy().into_owned());
+        insert_project.bind((4, origin.as_deref()))?;
+        insert_project.next()?;
+        if let Some(insert_text) = insert_text.as_mut() {
+            let values: Vec<&str> = project
+                .topics
+                .values()
+                .flatten()
+                .map(String::as_str)
+                .collect();
+            insert_text.reset()?;
+            insert_text.bind((1, id))?;
+            insert_text.bind((2, project.source.as_str()))?;
+            insert_text.bind((3, project.metadata.name.as_deref()))?;
+            insert_text.bind((4, project.metadata.description.as_deref()))?;
+            insert_text.bind((5, project.metadata.tags.join(" ").as_str()))?;
+            insert_text.bind((6, values.join(" ").as_str()))?;
+            insert_text.next()?;
+        }
+        for (topic, values) in project.topics.iter() {
+            if values.is_empty() {
+                insert_not_applicable.reset()?;
+                insert_not_applicable.bind((1, id))?;
+                insert_not_applicable.bind((2, topic.as_str()))?;
+                insert_not_applicable.next()?;
+            }
+            for value in values {
+                insert_topic.reset()?;
+                insert_topic.bind((1, id))?;
+                insert_topic.bind((2, topic.as_str()))?;
+                insert_topic.bind((3, value.as_str()))?;
+                insert_topic.next()?;
             }
-            println!("Inserting Project: {project:#?}");
-            projects.push(project);
         }
-        projects
     }
+    Ok(())
 }
 
-#[derive(Clone, Deserialize, Serialize, Debug)]
-pub struct Project {
-    pub source: String,
-    #[serde(flatten)]
-    pub topics: HashMap<String, String>,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{
+        tests::{catalog, option, parameter, project, sources, text_sources},
+        CatalogFormat,
+    };
+
+    const HOSTILE_TOPIC: &str = r#"lang"uage'); DROP TABLE "projects"; --"#;
+    const HOSTILE_VALUE: &str = "O'Brien\"; DELETE FROM topics; --";
+
+    fn hostile_catalog() -> Vec<Project> {
+        vec![
+            project(
+                "https://example.com/it's \"quoted\"",
+                &[(HOSTILE_TOPIC, HOSTILE_VALUE), ("with space", "a b")],
+            ),
+            project("/tmp/plain", &[(HOSTILE_TOPIC, "Rust"), ("Language", "Go")]),
+            project("'; --", &[("language", "%_*")]),
+            project("no topics", &[]),
+        ]
+    }
+
+    #[test]
+    fn database_names_are_stable() {
+        assert_eq!(hash(""), "cbf29ce484222325");
+        assert_eq!(hash("a"), "af63dc4c8601ec8c");
+        assert_eq!(hash("/home/me/data.toml"), hash("/home/me/data.toml"));
+        assert_ne!(hash("/home/me/data.toml"), hash("/home/me/other.toml"));
+    }
+
+    #[test]
+    fn persistent_catalog_follows_its_file() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let database: PathBuf = directory.path().join("catalog.sqlite");
+        let catalog: PathBuf = directory.path().join("catalog.toml");
+        let catalogs: &[PathBuf] = std::slice::from_ref(&catalog);
+
+        fs::write(
+            &catalog,
+            "[[projects]]\nsource = \"first\"\nlanguage = \"Rust\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            sources(&Data::open(&database, catalogs).unwrap(), &[]),
+            vec!["first"]
+        );
+
+        // Same size, new content
+        fs::write(
+            &catalog,
+            "[[projects]]\nsource = \"other\"\nlanguage = \"Rust\"\n",
+        )
+        .unwrap();
+        let data: Data = Data::open(&database, catalogs).unwrap();
+        assert_eq!(sources(&data, &[]), vec!["other"]);
+        let options = data
+            .retrieve_topic_options("language", &parameter(&data, &[]))
+            .unwrap();
+        assert_eq!(options, vec![option("Rust", 1)]);
+
+        fs::remove_file(&catalog).unwrap();
+        assert!(matches!(
+            Data::open(&database, catalogs),
+            Err(Error::Io(path, _)) if path == catalog
+        ));
+    }
+
+    #[test]
+    fn unchanged_catalog_is_not_imported_again() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let database: PathBuf = directory.path().join("catalog.sqlite");
+        let catalog: PathBuf = directory.path().join("catalog.toml");
+        let catalogs: &[PathBuf] = std::slice::from_ref(&catalog);
+        let content: &str = "[[projects]]\nsource = \"first\"\n";
+        fs::write(&catalog, content).unwrap();
+        Data::open(&database, catalogs).unwrap();
+
+        // Removed behind its back, so an import would bring the project back
+        let connection: Connection = sqlite::open(&database).unwrap();
+        connection.execute(r#"DELETE FROM "projects";"#).unwrap();
+        let data: Data = Data::open(&database, catalogs).unwrap();
+        assert!(data.search(&parameter(&data, &[])).unwrap().is_empty());
+
+        // Touched without changing the content
+        fs::write(&catalog, content).unwrap();
+        let file = fs::File::options().write(true).open(&catalog).unwrap();
+        file.set_modified(UNIX_EPOCH).unwrap();
+        let data: Data = Data::open(&database, catalogs).unwrap();
+        assert!(data.search(&parameter(&data, &[])).unwrap().is_empty());
+        let stored: Vec<Fingerprint> = stored_fingerprints(&connection).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].modified, 0);
+        assert_eq!(stored[0].hash, Some(hash(content)));
+    }
+
+    #[test]
+    fn persistent_catalog_follows_manifests_and_catalogs() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let database: PathBuf = directory.path().join("catalog.sqlite");
+        let template: PathBuf = directory.path().join("template");
+        fs::create_dir(&template).unwrap();
+        let manifest: PathBuf = template.join(manifest::MANIFEST_FILE);
+        fs::write(&manifest, "[topics]\nlanguage = \"Rust\"\n").unwrap();
+        let first: PathBuf = directory.path().join("first.toml");
+        fs::write(&first, "[[projects]]\nsource = \"template\"\n").unwrap();
+        let second: PathBuf = directory.path().join("second.toml");
+        fs::write(&second, "[[projects]]\nsource = \"other\"\n").unwrap();
+        let languages = |data: &Data| {
+            data.retrieve_topic_options("language", &parameter(data, &[]))
+                .unwrap()
+        };
+
+        let data: Data = Data::open(&database, std::slice::from_ref(&first)).unwrap();
+        assert_eq!(languages(&data), vec![option("Rust", 1)]);
+        fs::write(&manifest, "[topics]\nlanguage = \"Go\"\n").unwrap();
+        let data: Data = Data::open(&database, std::slice::from_ref(&first)).unwrap();
+        assert_eq!(languages(&data), vec![option("Go", 1)]);
+
+        // Another set of catalogs in the same database is imported in place of the last one
+        let data: Data = Data::open(&database, std::slice::from_ref(&second)).unwrap();
+        assert_eq!(sources(&data, &[]), vec!["other"]);
+        let data: Data = Data::open(&database, &[first, second]).unwrap();
+        assert_eq!(sources(&data, &[]), vec!["template", "other"]);
+    }
+
+    #[test]
+    fn hostile_catalog_round_trips() {
+        let data: Data = Data::new(&catalog(hostile_catalog())).unwrap();
+        let found: Vec<Arc<Project>> = data.search(&parameter(&data, &[])).unwrap();
+        let expected: Vec<Project> = hostile_catalog();
+        assert_eq!(found.len(), expected.len());
+        for (found, expected) in found.iter().zip(&expected) {
+            assert_eq!(found.source, expected.source);
+            assert_eq!(found.topics, expected.topics);
+        }
+    }
+
+    #[test]
+    fn hostile_catalog_survives_every_format() {
+        let catalog: Projects = catalog(hostile_catalog());
+        for (format, file) in [
+            (CatalogFormat::Toml, "catalog.toml"),
+            (CatalogFormat::Json, "catalog.json"),
+            (CatalogFormat::Yaml, "catalog.yaml"),
+            // Detected from the content
+            (CatalogFormat::Json, "catalog"),
+            (CatalogFormat::Yaml, "catalog"),
+        ] {
+            let written: String = catalog.write(format).unwrap();
+            let read: Projects = Projects::parse(Path::new(file), &written).unwrap();
+            assert_eq!(read, catalog, "{format:?} as {file}");
+        }
+    }
+
+    #[test]
+    fn hostile_filters_match_exactly() {
+        let data: Data = Data::new(&catalog(hostile_catalog())).unwrap();
+
+        let found = data
+            .search(&parameter(&data, &[(HOSTILE_TOPIC, HOSTILE_VALUE)]))
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].source, "https://example.com/it's \"quoted\"");
+
+        let found = data
+            .search(&parameter(&data, &[("language", "%_*")]))
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].source, "'; --");
+
+        let options = data
+            .retrieve_topic_options(HOSTILE_TOPIC, &parameter(&data, &[]))
+            .unwrap();
+        assert_eq!(
+            options,
+            vec![
+                option(HOSTILE_VALUE, 1),
+                option("Rust", 1),
+                option("(unspecified)", 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn topics_differing_in_case_stay_apart() {
+        let data: Data = Data::new(&catalog(hostile_catalog())).unwrap();
+        let options = data
+            .retrieve_topic_options("Language", &parameter(&data, &[]))
+            .unwrap();
+        assert_eq!(options, vec![option("Go", 1), option("(unspecified)", 3)]);
+        let options = data
+            .retrieve_topic_options("language", &parameter(&data, &[]))
+            .unwrap();
+        assert_eq!(options, vec![option("%_*", 1), option("(unspecified)", 3)]);
+    }
+
+    #[test]
+    fn unknown_hostile_value_is_an_error() {
+        let data: Data = Data::new(&catalog(hostile_catalog())).unwrap();
+        let err = data
+            .search(&parameter(&data, &[("language", "' OR 1=1 --")]))
+            .unwrap_err();
+        assert!(matches!(err, Error::UnknownFilterValue(topic, ..) if topic == "language"));
+    }
+
+    #[test]
+    fn multi_valued_topics_match_any_value() {
+        let mut both: Project = project("both", &[("database", "PostgreSQL")]);
+        both.topics
+            .get_mut("database")
+            .unwrap()
+            .push("MySql".to_string());
+        let projects = vec![project("postgres", &[("database", "PostgreSQL")]), both];
+        let data: Data = Data::new(&catalog(projects)).unwrap();
+
+        assert_eq!(
+            sources(&data, &[("database", "PostgreSQL")]),
+            vec!["postgres", "both"]
+        );
+        assert_eq!(sources(&data, &[("database", "mysql")]), vec!["both"]);
+
+        let options = data
+            .retrieve_topic_options("database", &parameter(&data, &[]))
+            .unwrap();
+        assert_eq!(options, vec![option("MySql", 1), option("PostgreSQL", 2)]);
+        let found = data.search(&parameter(&data, &[])).unwrap();
+        assert_eq!(found[1].topics["database"], vec!["PostgreSQL", "MySql"]);
+    }
+
+    #[test]
+    fn both_backends_agree() {
+        let tree = crate::projects::SearchTree::new(catalog(hostile_catalog())).unwrap();
+        let data: Data = Data::new(&catalog(hostile_catalog())).unwrap();
+        let backends: [&dyn CatalogBackend; 2] = [&tree, &data];
+        for filters in [
+            &[][..],
+            &[(HOSTILE_TOPIC, HOSTILE_VALUE)],
+            &[(HOSTILE_TOPIC, "rust")],
+            &[("language", "%_*")],
+        ] {
+            assert_eq!(
+                sources(&tree, filters),
+                sources(&data, filters),
+                "{filters:?}"
+            );
+        }
+        for backend in backends {
+            assert_eq!(backend.get_topics(), data.get_topics());
+            let options = backend
+                .retrieve_topic_options(HOSTILE_TOPIC, &parameter(&data, &[]))
+                .unwrap();
+            assert_eq!(
+                options,
+                vec![
+                    option(HOSTILE_VALUE, 1),
+                    option("Rust", 1),
+                    option("(unspecified)", 2)
+                ]
+            );
+            // Options only come from the projects matching the filters of the other topics
+            let options = backend
+                .retrieve_topic_options(HOSTILE_TOPIC, &parameter(&data, &[("Language", "go")]))
+                .unwrap();
+            assert_eq!(options, vec![option("Rust", 1)]);
+            // The filter of the topic itself doesn't narrow its options
+            let options = backend
+                .retrieve_topic_options(
+                    HOSTILE_TOPIC,
+                    &parameter(&data, &[(HOSTILE_TOPIC, HOSTILE_VALUE)]),
+                )
+                .unwrap();
+            assert_eq!(
+                options,
+                vec![
+                    option(HOSTILE_VALUE, 1),
+                    option("Rust", 1),
+                    option("(unspecified)", 2)
+                ]
+            );
+            let found = backend
+                .search_text(&parameter(&data, &[]), "plain")
+                .unwrap();
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].source, "/tmp/plain");
+            let project = backend.get_project("'; --").unwrap().unwrap();
+            assert_eq!(project.topics["language"], vec!["%_*"]);
+            assert!(backend.get_project("missing").unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn text_search_ranks_and_filters() {
+        let mut named: Project = project("/templates/api", &[("language", "Go")]);
+        named.metadata.name = Some("GraphQL API".to_string());
+        let mut described: Project = project("/templates/web", &[("language", "Rust")]);
+        described.metadata.description = Some("Web app calling a graphql server".to_string());
+        described.metadata.tags = vec!["auth".to_string()];
+        let projects = vec![
+            described,
+            named,
+            project("/templates/other", &[("language", "Rust")]),
+        ];
+        let data: Data = Data::new(&catalog(projects)).unwrap();
+
+        let sources = |filters: &[(&str, &str)], text: &str| text_sources(&data, filters, text);
+        // A match in the name ranks above one in the description
+        assert_eq!(
+            sources(&[], "graph"),
+            vec!["/templates/api", "/templates/web"]
+        );
+        // Matching more words ranks higher
+        assert_eq!(
+            sources(&[], "graphql auth"),
+            vec!["/templates/web", "/templates/api"]
+        );
+        assert_eq!(
+            sources(&[("language", "Rust")], "graphql"),
+            vec!["/templates/web"]
+        );
+        assert!(sources(&[("language", "Go")], "rust").is_empty());
+        assert_eq!(sources(&[], "OTHER"), vec!["/templates/other"]);
+        assert_eq!(sources(&[], " \"* "), sources(&[], ""));
+        assert_eq!(
+            sources(&[], r#"NEAR("api" x) OR -"#),
+            vec!["/templates/api"],
+        );
+    }
+
+    #[test]
+    fn aliases_canonicalize_values_and_filters() {
+        let aliases: Aliases = toml::from_str(
+            r#"
+            database = { PostgreSQL = ["postgres", "pg"] }
+            "#,
+        )
+        .unwrap();
+        let mut both: Project = project("/templates/both", &[("database", "MySql")]);
+        both.topics
+            .get_mut("database")
+            .unwrap()
+            .extend(["Postgres".to_string(), "postgresql".to_string()]);
+        let mut catalog = Projects {
+            projects: vec![project("/templates/pg", &[("database", "pg")]), both],
+            aliases,
+            ..Default::default()
+        };
+        catalog.canonicalize();
+        let data: Data = Data::new(&catalog).unwrap();
+
+        let options = data
+            .retrieve_topic_options("database", &parameter(&data, &[]))
+            .unwrap();
+        assert_eq!(options, vec![option("MySql", 1), option("PostgreSQL", 2)]);
+        let found = data
+            .search(&parameter(&data, &[("database", "POSTGRES")]))
+            .unwrap();
+        assert_eq!(found.len(), 2);
+        // Spellings of the same value are only kept once
+        assert_eq!(found[1].topics["database"], vec!["MySql", "PostgreSQL"]);
+
+        let err = data
+            .search(&parameter(&data, &[("database", "PostgresQL ")]))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnknownFilterValue(_, _, Some(suggestion)) if suggestion == "PostgreSQL"
+        ));
+        let err = data
+            .search(&parameter(&data, &[("database", "oracle")]))
+            .unwrap_err();
+        assert!(matches!(err, Error::UnknownFilterValue(_, _, None)));
+    }
+
+    #[test]
+    fn topics_follow_their_declared_order() {
+        let mut catalog: Projects = catalog(vec![project(
+            "/templates/one",
+            &[("b", "1"), ("a", "1"), ("d", "1"), ("c", "1")],
+        )]);
+        catalog.topics = toml::from_str(
+            r#"
+            c = { order = 1 }
+            d = { order = 0, values = ["2", "1"] }
+            a = {}
+            "#,
+        )
+        .unwrap();
+        let data: Data = Data::new(&catalog).unwrap();
+        assert_eq!(data.get_topics(), ["d", "c", "a", "b"]);
+        assert_eq!(data.get_declared(), &catalog.topics);
+    }
 }
@@ -1,163 +1,166 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    cell::OnceCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
 
-use serde::Deserialize;
-use std::sync::Arc;
+use crate::{
+    catalog::{
+        order_values, suggest, Aliases, CatalogBackend, Filter, Project, Projects, SearchParameter,
+        Topic, TopicOption,
+    },
+    error::Error,
+    project_sqlite::Data,
+};
 
-use crate::error::Error;
+/// Set of projects, one bit for the position of each in the catalog
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bitset(Vec<u64>);
 
-#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
-pub struct Projects {
-    pub projects: Vec<Project>,
-}
-
-// order of search is platform, language, database then deployment
-#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
-pub struct Project {
-    pub source: String,
-    #[serde(flatten)]
-    pub topics: HashMap<String, String>,
-}
+impl Bitset {
+    /// Every position below `len`
+    pub fn full(len: usize) -> Self {
+        let mut words: Vec<u64> = vec![u64::MAX; len.div_ceil(64)];
+        if let (Some(last), rest @ 1..) = (words.last_mut(), len % 64) {
+            *last = (1 << rest) - 1;
+        }
+        Self(words)
+    }
 
-/// Search nodes for "platform", "language", "database" and "deployment" respectively
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct SearchNode {
-    /// For debugging. What topic is this Search node associated with
-    pub topic: String,
-    /// For debugging. What search value this node represents
-    pub expected: Option<String>,
-    /// For searching. The values that this query so far represents at the end of a search (final node)
-    pub found: Vec<Arc<Project>>,
-    /// Next nodes according to the expected value for the next topic
-    pub next: HashMap<Option<String>, Arc<SearchNode>>,
-}
+    pub fn insert(&mut self, position: usize) {
+        let word: usize = position / 64;
+        if self.0.len() <= word {
+            self.0.resize(word + 1, 0);
+        }
+        self.0[word] |= 1 << (position % 64);
+    }
 
-impl SearchNode {
-    pub fn filter(
-        projects: Vec<Arc<Project>>,
-        expected: &Option<String>,
-        topic: &str,
-        node_depth: usize,
-    ) -> Vec<Arc<Project>> {
-        println!("Function SearchNode::filter\nnode_depth:{node_depth}\nexpected:{expected:?}\ntopic:{topic}\nprojects:{projects:?}\n");
-        let mut found: Vec<Arc<Project>> = Vec::with_capacity(projects.len());
-        match &expected {
-            None => found.extend(projects),
-            Some(expected) => {
-                for project in projects {
-                    if let Some(project_topic) = project.topics.get(topic) {
-                        if expected.eq_ignore_ascii_case(project_topic) {
-                            found.push(project);
-                        }
-                    }
-                }
-            }
+    pub fn union_with(&mut self, other: &Self) {
+        if self.0.len() < other.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+        for (word, other) in self.0.iter_mut().zip(&other.0) {
+            *word |= other;
         }
-        found
     }
 
-    pub fn create_children(
-        topics: &[String],
-        found: &[Arc<Project>],
-        next: &mut HashMap<Option<String>, Arc<SearchNode>>,
-        node_depth: usize,
-    ) -> Result<(), Error> {
-        println!(
-            "Function SearchNode::create_children\nnode_depth:{node_depth}\ntopics:{topics:?}\nfound:{found:?}\n"
-        );
-        if let Some(topic) = topics.first() {
-            let search_node: SearchNode =
-                SearchNode::new(found.to_vec(), topics.to_vec(), None, node_depth)?;
-            let search_node: Arc<SearchNode> = Arc::new(search_node);
-            next.insert(None, search_node);
-            // Creating next nodes
-            for project in found {
-                println!("Looping Project, node_depth {node_depth}: {project:?}\ntopic:{topic:?}\ntopics:{topics:?}\n");
-                // topic is a single value for each node that doesn't change
-                // let expected = match topic {
-                //     Topic::Database => project.database.clone(),
-                //     Topic::Deployment => project.deployment.clone(),
-                //     Topic::Language => project.language.clone(),
-                //     Topic::Platform => project.platform.clone(),
-                // };
-                // this is accessing out of bounds
-
-                let expected = {
-                    if !project.topics.contains_key(topic) {
-                        // If project doesn't have the key, ignore it
-                        None
-                    } else {
-                        Some(project.topics[topic].to_string())
-                    }
-                };
-                // let expected: String = project.topics[topic].to_string();
-                // // so there will be no inconsistencies on the key due to the selection above
-                // let expected: Option<String> = Some(expected);
-                if next.contains_key(&expected) {
-                    // no need to replace an existing key
-                    continue;
-                }
-                let search_node: SearchNode = SearchNode::new(
-                    found.to_vec(),
-                    topics.to_vec(),
-                    expected.clone(),
-                    node_depth,
-                )?;
-                let search_node: Arc<SearchNode> = Arc::new(search_node);
-                next.insert(expected, search_node);
-            }
+    /// Removes the positions `other` has
+    pub fn difference_with(&mut self, other: &Self) {
+        for (word, other) in self.0.iter_mut().zip(&other.0) {
+            *word &= !other;
         }
-        Ok(())
     }
 
-    pub fn new(
-        projects: Vec<Arc<Project>>,
-        mut topics: Vec<String>,
-        expected: Option<String>,
-        node_depth: usize,
-    ) -> Result<Self, Error> {
-        println!("Function SearchNode::new\nnode_depth:{node_depth}\ntopics: {topics:?}\nexpected: {expected:?}\nprojects:{projects:?}\n");
-        let mut next: HashMap<Option<String>, Arc<SearchNode>> =
-            HashMap::with_capacity(projects.len());
-        if topics.is_empty() {
-            return Err(Error::SearchNodeEmptyTopics);
+    pub fn intersect_with(&mut self, other: &Self) {
+        self.0.truncate(other.0.len());
+        for (word, other) in self.0.iter_mut().zip(&other.0) {
+            *word &= other;
         }
-        let topic: String = topics.remove(0);
-        let found: Vec<Arc<Project>> = Self::filter(projects, &expected, &topic, node_depth);
-        Self::create_children(&topics, &found, &mut next, node_depth + 1)?;
-        Ok(Self {
-            topic,
-            expected,
-            found,
-            next,
+    }
+
+    /// How many positions both sets have, without building their intersection
+    pub fn intersection_count(&self, other: &Self) -> usize {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .map(|(word, other)| (word & other).count_ones() as usize)
+            .sum()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|word| *word == 0)
+    }
+
+    /// Positions in the set, in increasing order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(index, word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| index * 64 + bit)
         })
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Inverted index of a topic, the projects having each of its values
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct TopicIndex {
+    /// By value as written in the catalog, the spellings listed as options
+    values: BTreeMap<String, Bitset>,
+    /// By value in ASCII lowercase, matched by filters so case is ignored
+    folded: HashMap<String, Bitset>,
+    /// Projects not mentioning the topic, see `Filter::Unspecified`
+    unspecified: Bitset,
+    /// Projects with an empty array for the topic, see `Filter::NotApplicable`
+    not_applicable: Bitset,
+}
+
+/// Catalog searched in memory, with an inverted index of each topic. A search intersects the
+/// projects of the value of each filter.
+///
+/// The name is from when the projects were kept in a tree of topic values. It stays as the public
+/// name of the in-memory backend, which the crate root exports.
 pub struct SearchTree {
-    next: HashMap<Option<String>, Arc<SearchNode>>,
     topics: Vec<String>,
     projects: Vec<Arc<Project>>,
+    /// Position of each project by source
+    sources: HashMap<String, usize>,
+    /// Index of each topic, every topic of `topics` has one
+    topic_indexes: HashMap<String, TopicIndex>,
+    /// Applied to the filters, the projects must already be canonical
+    aliases: Aliases,
+    /// The `[topics]` section of the catalog
+    declared: BTreeMap<String, Topic>,
+    /// Full-text index of the projects, only built once a text search is made
+    index: OnceCell<Data>,
 }
 
 impl SearchTree {
-    pub fn new(projects: Vec<Project>) -> Result<Self, Error> {
-        println!("Function SearchTree::new\nprojects:{projects:?}\n");
-        let projects: Vec<Arc<Project>> = projects.into_iter().map(Arc::new).collect();
-        let mut topics: HashSet<String> = HashSet::new();
-        for project in projects.iter() {
-            for topic in project.topics.keys() {
-                topics.insert(topic.to_string());
+    /// Index of the projects of `catalog`, which must already be canonical
+    pub fn new(catalog: Projects) -> Result<Self, Error> {
+        let topics: Vec<String> = catalog.topic_order();
+        let projects: Vec<Arc<Project>> = catalog.projects.into_iter().map(Arc::new).collect();
+        let mut sources: HashMap<String, usize> = HashMap::with_capacity(projects.len());
+        let mut topic_indexes: HashMap<String, TopicIndex> = HashMap::new();
+        for (position, project) in projects.iter().enumerate() {
+            sources.insert(project.source.clone(), position);
+            for (topic, values) in &project.topics {
+                let index: &mut TopicIndex = topic_indexes.entry(topic.clone()).or_default();
+                if values.is_empty() {
+                    index.not_applicable.insert(position);
+                }
+                for value in values {
+                    index
+                        .values
+                        .entry(value.clone())
+                        .or_default()
+                        .insert(position);
+                    index
+                        .folded
+                        .entry(value.to_ascii_lowercase())
+                        .or_default()
+                        .insert(position);
+                }
+            }
+        }
+        for (topic, index) in topic_indexes.iter_mut() {
+            for (position, project) in projects.iter().enumerate() {
+                if !project.topics.contains_key(topic) {
+                    index.unspecified.insert(position);
+                }
             }
         }
-        let topics: Vec<String> = topics.into_iter().collect();
-        let mut next: HashMap<Option<String>, Arc<SearchNode>> =
-            HashMap::with_capacity(projects.len());
-        SearchNode::create_children(&topics, &projects, &mut next, 0)?;
         Ok(Self {
-            next,
             topics,
             projects,
+            sources,
+            topic_indexes,
+            aliases: catalog.aliases,
+            declared: catalog.topics,
+            index: OnceCell::new(),
         })
     }
 
@@ -165,109 +168,369 @@ impl SearchTree {
         &self.projects[..]
     }
 
-    pub fn get_topics(&self) -> &[String] {
-        &self.topics
+    fn index(&self) -> Result<&Data, Error> {
+        if let Some(index) = self.index.get() {
+            return Ok(index);
+        }
+        let index: Data = Data::new(&Projects {
+            projects: self
+                .projects
+                .iter()
+                .map(|project| Project::clone(project))
+                .collect(),
+            aliases: self.aliases.clone(),
+            topics: self.declared.clone(),
+            include: Vec::new(),
+        })?;
+        Ok(self.index.get_or_init(|| index))
     }
 
-    fn next_node(
-        next: &HashMap<Option<String>, Arc<SearchNode>>,
-        topic: &String,
-        parameter: &SearchParameter,
-    ) -> Option<Arc<SearchNode>> {
-        println!("Function next_node topic: {topic}\nparameter:{parameter:?}\n");
-        let expected: Option<String> = parameter.topics[topic].to_owned();
-        // let expected: Option<String> = match topic {
-        //     Topic::Database => parameter.database.clone(),
-        //     Topic::Deployment => parameter.deployment.clone(),
-        //     Topic::Language => parameter.language.clone(),
-        //     Topic::Platform => parameter.platform.clone(),
-        // };
-        next.get(&expected).cloned()
+    /// Every filter must name a known topic and only values that some project has
+    fn validate(&self, parameter: &SearchParameter) -> Result<(), Error> {
+        for (topic, filter) in parameter.topics.iter() {
+            let Some(index) = self.topic_indexes.get(topic) else {
+                return Err(Error::MissingTopic(topic.to_string()));
+            };
+            for value in filter.iter().flat_map(Filter::values) {
+                if !index.folded.contains_key(&value.to_ascii_lowercase()) {
+                    let mut candidates: Vec<&str> = self.aliases.spellings(topic);
+                    candidates.extend(index.values.keys().map(String::as_str));
+                    return Err(Error::UnknownFilterValue(
+                        topic.to_string(),
+                        value.to_string(),
+                        suggest(value, candidates),
+                    ));
+                }
+            }
+        }
+        Ok(())
     }
 
-    pub fn search(&self, parameter: &SearchParameter) -> Vec<Arc<Project>> {
-        println!("Function search parameter:{parameter:?}");
-        let topics: &Vec<String> = &self.topics;
-        // If there are no filters in the search parameters
-        if parameter.is_empty() {
-            self.projects.clone()
-        } else {
-            let mut next: Arc<SearchNode> =
-                Self::next_node(&self.next, &topics[0], parameter).unwrap();
-            for topic in &topics[1..] {
-                next = Self::next_node(&next.next, topic, parameter).unwrap();
+    /// Projects matching every filter of `parameter` except the one of `except`. `parameter`
+    /// must be canonical and valid.
+    fn matching(&self, parameter: &SearchParameter, except: Option<&str>) -> Bitset {
+        let mut found: Bitset = Bitset::full(self.projects.len());
+        for (topic, filter) in parameter.topics.iter() {
+            let Some(filter) = filter else {
+                continue;
+            };
+            if Some(topic.as_str()) == except {
+                continue;
             }
-            next.found.clone()
+            found.intersect_with(&self.filtered(&self.topic_indexes[topic], filter));
         }
+        found
     }
 
-    // This will not work. Will need to implement a different way of indexing the data that works for each unique topic
-    // Maybe one tree for each unique topic?
-    // Maybe one type of composite tree that can link per topic instead of project
-    /// retrieve all possible alternatives for a specific topic
-    pub fn retrieve_topic_options(
+    /// Projects matching `filter` on the topic of `index`
+    fn filtered(&self, index: &TopicIndex, filter: &Filter) -> Bitset {
+        match filter {
+            Filter::Value(value) => index
+                .folded
+                .get(&value.to_ascii_lowercase())
+                .cloned()
+                .unwrap_or_default(),
+            Filter::Unspecified => index.unspecified.clone(),
+            Filter::NotApplicable => index.not_applicable.clone(),
+            Filter::AnyOf(filters) => {
+                let mut found: Bitset = Bitset::default();
+                for filter in filters {
+                    found.union_with(&self.filtered(index, filter));
+                }
+                found
+            }
+            Filter::NoneOf(filters) => {
+                let mut found: Bitset = Bitset::full(self.projects.len());
+                for filter in filters {
+                    found.difference_with(&self.filtered(index, filter));
+                }
+                found
+            }
+        }
+    }
+}
+
+impl CatalogBackend for SearchTree {
+    fn get_topics(&self) -> &[String] {
+        &self.topics
+    }
+
+    fn get_aliases(&self) -> &Aliases {
+        &self.aliases
+    }
+
+    fn get_declared(&self) -> &BTreeMap<String, Topic> {
+        &self.declared
+    }
+
+    fn get_project(&self, source: &str) -> Result<Option<Arc<Project>>, Error> {
+        Ok(self
+            .sources
+            .get(source)
+            .map(|position| self.projects[*position].clone()))
+    }
+
+    fn search(&self, parameter: &SearchParameter) -> Result<Vec<Arc<Project>>, Error> {
+        let parameter: &SearchParameter = &self.aliases.parameter(parameter);
+        self.validate(parameter)?;
+        Ok(self
+            .matching(parameter, None)
+            .iter()
+            .map(|position| self.projects[position].clone())
+            .collect())
+    }
+
+    /// Ranked by the full-text index of `Data`, built the first time
+    fn search_text(
         &self,
-        expected_topic: &String,
-        mut parameter: SearchParameter,
-    ) -> Vec<String> {
-        println!("Function expected_topic: {expected_topic}\nparameter:{parameter:?}\n");
-        // Must include all possible options for the topic that is being expected
-        // So will filter None of it
-        parameter.topics.insert(expected_topic.to_string(), None);
-        // match expected_topic {
-        //     Topic::Database => parameter.database = None,
-        //     Topic::Deployment => parameter.deployment = None,
-        //     Topic::Language => parameter.language = None,
-        //     Topic::Platform => parameter.platform = None,
-        // };
-        let topics: &Vec<String> = &self.topics;
-        if *expected_topic == topics[0] && parameter.is_empty() {
-            let mut output: Vec<String> = Vec::with_capacity(self.next.len());
-            self.next.keys().for_each(|key| match key {
-                None => {}
-                Some(value) => output.push(value.to_string()),
-            });
+        parameter: &SearchParameter,
+        text: &str,
+    ) -> Result<Vec<Arc<Project>>, Error> {
+        Ok(self
+            .index()?
+            .search_text(parameter, text)?
+            .into_iter()
+            .filter_map(|found| self.sources.get(&found.source))
+            .map(|position| self.projects[*position].clone())
+            .collect())
+    }
 
-            return output;
-        }
-        let mut next: Arc<SearchNode> =
-            Self::next_node(&self.next, &topics[0], &parameter).unwrap();
-        for topic in &topics[1..] {
-            if *expected_topic == *topic {
-                let mut output: Vec<String> = Vec::with_capacity(next.next.len());
-                next.next.keys().for_each(|key| match key {
-                    None => {}
-                    Some(value) => output.push(value.to_string()),
-                });
-                return output;
+    fn retrieve_topic_options(
+        &self,
+        topic: &str,
+        parameter: &SearchParameter,
+    ) -> Result<Vec<TopicOption>, Error> {
+        let Some(index) = self.topic_indexes.get(topic) else {
+            return Err(Error::MissingTopic(topic.to_string()));
+        };
+        let mut parameter: SearchParameter = self.aliases.parameter(parameter);
+        // Every option of the topic being chosen is offered, whatever it is filtered by
+        parameter.topics.insert(topic.to_string(), None);
+        self.validate(&parameter)?;
+        let candidates: Bitset = self.matching(&parameter, Some(topic));
+        // Spellings differing in case are one option, since filters match them all. It is shown
+        // with the first spelling a candidate has, in byte order like in `Data`.
+        let mut folded: HashSet<String> = HashSet::new();
+        let mut options: Vec<TopicOption> = Vec::new();
+        for (value, projects) in index.values.iter() {
+            let key: String = value.to_ascii_lowercase();
+            if projects.intersection_count(&candidates) == 0 || folded.contains(&key) {
+                continue;
             }
-            next = Self::next_node(&next.next, topic, &parameter).unwrap();
+            options.push(TopicOption {
+                value: value.clone(),
+                count: index.folded[&key].intersection_count(&candidates),
+            });
+            folded.insert(key);
         }
-        unreachable!();
+        let mut options: Vec<TopicOption> = order_values(options, self.declared.get(topic));
+        options.extend(TopicOption::without_value(
+            index.unspecified.intersection_count(&candidates),
+            index.not_applicable.intersection_count(&candidates),
+        ));
+        Ok(options)
     }
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
-pub struct SearchParameter {
-    #[serde(flatten)]
-    pub topics: HashMap<String, Option<String>>,
-}
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::catalog::{
+        tests::{catalog, option, parameter, project, sources},
+        CatalogFormat,
+    };
 
-impl SearchParameter {
-    pub fn new(topic_list: Vec<String>) -> Self {
-        let mut topics: HashMap<String, Option<String>> = HashMap::with_capacity(topic_list.len());
-        for topic in topic_list {
-            topics.insert(topic, None);
+    fn positions(set: &Bitset) -> Vec<usize> {
+        set.iter().collect()
+    }
+
+    fn set(positions: &[usize]) -> Bitset {
+        let mut set: Bitset = Bitset::default();
+        for position in positions {
+            set.insert(*position);
         }
-        Self { topics }
+        set
     }
-    pub fn is_empty(&self) -> bool {
-        let mut is_empty: bool = true;
-        self.topics.values().for_each(|value| {
-            if value.is_some() {
-                is_empty = false;
-            }
-        });
-        is_empty
+
+    #[test]
+    fn full_sets_stop_at_their_length() {
+        assert!(Bitset::full(0).is_empty());
+        for len in [1, 63, 64, 65, 128, 129] {
+            let full: Bitset = Bitset::full(len);
+            assert_eq!(full.len(), len);
+            assert_eq!(positions(&full), (0..len).collect::<Vec<usize>>());
+        }
+        assert_eq!(Bitset::full(64).0, vec![u64::MAX]);
+        assert_eq!(Bitset::full(65).0, vec![u64::MAX, 1]);
+    }
+
+    #[test]
+    fn operations_on_sets_of_unequal_lengths() {
+        let short: Bitset = set(&[0, 63]);
+        let long: Bitset = set(&[0, 64, 130]);
+
+        let mut difference: Bitset = long.clone();
+        difference.difference_with(&short);
+        assert_eq!(positions(&difference), vec![64, 130]);
+        let mut difference: Bitset = short.clone();
+        difference.difference_with(&long);
+        assert_eq!(positions(&difference), vec![63]);
+
+        let mut intersection: Bitset = long.clone();
+        intersection.intersect_with(&short);
+        assert_eq!(positions(&intersection), vec![0]);
+        let mut intersection: Bitset = short.clone();
+        intersection.intersect_with(&long);
+        assert_eq!(positions(&intersection), vec![0]);
+        assert_eq!(short.intersection_count(&long), 1);
+
+        let mut union: Bitset = short.clone();
+        union.union_with(&long);
+        assert_eq!(positions(&union), vec![0, 63, 64, 130]);
+        assert_eq!(union.len(), 4);
+    }
+
+    #[test]
+    fn missing_topics_are_unspecified_or_not_applicable() {
+        let catalog = || -> Projects {
+            Projects::parse(
+                Path::new("catalog.toml"),
+                r#"
+                [[projects]]
+                source = "aws"
+                platform = "Aws"
+                language = "Rust"
+
+                [[projects]]
+                source = "none"
+                platform = []
+                language = "Rust"
+                ci = []
+
+                [[projects]]
+                source = "unknown"
+                language = "Go"
+                "#,
+            )
+            .unwrap()
+        };
+        let tree = SearchTree::new(catalog()).unwrap();
+        let data: Data = Data::new(&catalog()).unwrap();
+        for backend in [&tree as &dyn CatalogBackend, &data] {
+            // Even a topic no project has a value for can be filtered
+            assert_eq!(
+                sources(backend, &[("ci", "(not applicable)")]),
+                vec!["none"]
+            );
+            assert_eq!(sources(backend, &[("platform", "(any)")]).len(), 3);
+            assert_eq!(
+                sources(backend, &[("platform", "(Unspecified)")]),
+                vec!["unknown"]
+            );
+            assert_eq!(
+                sources(backend, &[("platform", "(not applicable)")]),
+                vec!["none"]
+            );
+            // Combinations no project has are empty rather than an error
+            assert!(sources(
+                backend,
+                &[("platform", "(unspecified)"), ("language", "rust")]
+            )
+            .is_empty());
+            assert!(sources(backend, &[("platform", "aws"), ("language", "go")]).is_empty());
+
+            let options = backend
+                .retrieve_topic_options("platform", &parameter(backend, &[]))
+                .unwrap();
+            assert_eq!(
+                options,
+                vec![
+                    option("Aws", 1),
+                    option("(unspecified)", 1),
+                    option("(not applicable)", 1)
+                ]
+            );
+            let options = backend
+                .retrieve_topic_options("platform", &parameter(backend, &[("language", "rust")]))
+                .unwrap();
+            assert_eq!(
+                options,
+                vec![option("Aws", 1), option("(not applicable)", 1)]
+            );
+        }
+        let written: String = catalog().write(CatalogFormat::Toml).unwrap();
+        assert!(written.contains("platform = []"), "{written}");
+    }
+
+    #[test]
+    fn any_of_and_none_of_filters() {
+        let aliases: Aliases = toml::from_str(r#"language = { Rust = ["rs"] }"#).unwrap();
+        let catalog = || -> Projects {
+            let mut catalog: Projects = catalog(vec![
+                project("rust", &[("language", "Rust"), ("platform", "Aws")]),
+                project("go", &[("language", "Go"), ("platform", "Vercel")]),
+                project("python", &[("language", "Python")]),
+            ]);
+            catalog.aliases = aliases.clone();
+            catalog
+        };
+        let tree = SearchTree::new(catalog()).unwrap();
+        let data: Data = Data::new(&catalog()).unwrap();
+        for backend in [&tree as &dyn CatalogBackend, &data] {
+            assert_eq!(
+                sources(backend, &[("language", "rs|go")]),
+                vec!["rust", "go"]
+            );
+            assert_eq!(
+                sources(backend, &[("platform", "!vercel")]),
+                vec!["rust", "python"]
+            );
+            assert_eq!(
+                sources(backend, &[("platform", "!Vercel|(unspecified)")]),
+                vec!["rust"]
+            );
+            assert_eq!(
+                sources(
+                    backend,
+                    &[("language", "!Python"), ("platform", "Aws|Vercel")]
+                ),
+                vec!["rust", "go"]
+            );
+            assert!(sources(backend, &[("language", "Python|Go"), ("platform", "Aws")]).is_empty());
+            let err = backend
+                .search(&parameter(backend, &[("language", "Rust|Pyton")]))
+                .unwrap_err();
+            assert!(matches!(err, Error::UnknownFilterValue(_, value, _) if value == "Pyton"));
+        }
+    }
+
+    #[test]
+    fn values_differing_in_case_are_one_option() {
+        let projects: Projects = catalog(vec![
+            project("a", &[("language", "rust"), ("platform", "Aws")]),
+            project("b", &[("language", "Rust"), ("platform", "Vercel")]),
+            project("c", &[("language", "Go"), ("platform", "Aws")]),
+        ]);
+        let data: Data = Data::new(&projects).unwrap();
+        let tree = SearchTree::new(projects).unwrap();
+        for backend in [&data as &dyn CatalogBackend, &tree] {
+            let options = |filters: &[(&str, &str)]| {
+                backend
+                    .retrieve_topic_options("language", &parameter(backend, filters))
+                    .unwrap()
+            };
+            assert_eq!(options(&[]), vec![option("Go", 1), option("Rust", 2)]);
+            // Shown as the spelling of the projects left
+            assert_eq!(
+                options(&[("platform", "Aws")]),
+                vec![option("Go", 1), option("rust", 1)]
+            );
+            let found = backend
+                .search(&parameter(backend, &[("language", "Rust")]))
+                .unwrap();
+            assert_eq!(found.len(), options(&[])[1].count);
+        }
     }
 }
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use inquire::{Confirm, Select, Text};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+
+/// A value the user is asked for before generating, declared in the catalog as:
+///
+/// ```toml
+/// [[projects.variables]]
+/// name = "project_name"
+/// type = "text"
+/// prompt = "Name of the project"
+/// default = "my-project"
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Variable {
+    /// Name used in the placeholders, `{{name}}`
+    pub name: String,
+    /// Message shown when prompting, defaults to the name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(flatten)]
+    pub kind: VariableKind,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum VariableKind {
+    /// Free text
+    Text {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        default: Option<String>,
+    },
+    /// One of a fixed list of options
+    Select {
+        options: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        default: Option<String>,
+    },
+    /// Yes or no, rendered as `true` or `false`
+    Confirm {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        default: Option<bool>,
+    },
+}
+
+impl Variable {
+    fn message(&self) -> &str {
+        self.prompt.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Value used when not prompting, if the variable declares one
+    pub fn default_value(&self) -> Option<String> {
+        match &self.kind {
+            VariableKind::Text { default } | VariableKind::Select { default, .. } => {
+                default.clone()
+            }
+            VariableKind::Confirm { default } => default.map(|default| default.to_string()),
+        }
+    }
+
+    /// Makes sure a value given without prompting is one the variable accepts
+    fn check(&self, value: String) -> Result<String, Error> {
+        let valid: bool = match &self.kind {
+            VariableKind::Text { .. } => true,
+            VariableKind::Select { options, .. } => options.contains(&value),
+            VariableKind::Confirm { .. } => value == "true" || value == "false",
+        };
+        if !valid {
+            return Err(Error::InvalidVariable(self.name.clone(), value));
+        }
+        Ok(value)
+    }
+
+    /// Asks the user for the value of this variable
+    pub fn prompt(&self) -> Result<String, Error> {
+        let message: &str = self.message();
+        let value: String = match &self.kind {
+            VariableKind::Text { default } => {
+                let mut text: Text = Text::new(message);
+                if let Some(default) = default {
+                    text = text.with_default(default);
+                }
+                text.prompt()?
+            }
+            VariableKind::Select { options, default } => {
+                let cursor: usize = default
+                    .as_ref()
+                    .and_then(|default| options.iter().position(|option| option == default))
+                    .unwrap_or(0);
+                Select::new(message, options.clone())
+                    .with_starting_cursor(cursor)
+                    .prompt()?
+            }
+            VariableKind::Confirm { default } => {
+                let mut confirm: Confirm = Confirm::new(message);
+                if let Some(default) = default {
+                    confirm = confirm.with_default(*default);
+                }
+                confirm.prompt()?.to_string()
+            }
+        };
+        Ok(value)
+    }
+}
+
+/// Resolves the value of every variable. Values in `given` are used as they are (after checking
+/// them against the variable's type), the rest are asked for in order, or take their default
+/// when `interactive` is false.
+pub fn resolve(
+    variables: &[Variable],
+    mut given: HashMap<String, String>,
+    interactive: bool,
+) -> Result<HashMap<String, String>, Error> {
+    let mut values: HashMap<String, String> = HashMap::with_capacity(variables.len());
+    for variable in variables {
+        let value: String = match given.remove(&variable.name) {
+            Some(value) => variable.check(value)?,
+            None if interactive => variable.prompt()?,
+            None => variable
+                .default_value()
+                .ok_or_else(|| Error::MissingVariable(variable.name.clone()))?,
+        };
+        values.insert(variable.name.clone(), value);
+    }
+    // Values for names the template doesn't declare are still rendered
+    values.extend(given);
+    Ok(values)
+}
+
+/// Substitutes `{{name}}` placeholders (spaces inside the braces are allowed) with their values.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Renderer {
+    values: HashMap<String, String>,
+}
+
+impl Renderer {
+    pub fn new(values: HashMap<String, String>) -> Self {
+        Self { values }
+    }
+
+    /// Placeholders for names without a value are kept as they are, so unrelated
+    /// syntax like `${{ secrets.TOKEN }}` in CI files survives rendering.
+    pub fn render(&self, input: &str) -> String {
+        if self.values.is_empty() {
+            return input.to_string();
+        }
+        let mut output: String = String::with_capacity(input.len());
+        let mut rest: &str = input;
+        while let Some(start) = rest.find(OPEN) {
+            let (before, from_open) = rest.split_at(start);
+            output.push_str(before);
+            let inner: &str = &from_open[OPEN.len()..];
+            let Some(end) = inner.find(CLOSE) else {
+                rest = from_open;
+                break;
+            };
+            match self.values.get(inner[..end].trim()) {
+                Some(value) => output.push_str(value),
+                None => output.push_str(&from_open[..OPEN.len() + end + CLOSE.len()]),
+            }
+            rest = &inner[end + CLOSE.len()..];
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(name: &str, kind: VariableKind) -> Variable {
+        Variable {
+            name: name.to_string(),
+            prompt: None,
+            kind,
+        }
+    }
+
+    #[test]
+    fn render_substitutes_known_placeholders() {
+        let renderer: Renderer = Renderer::new(HashMap::from([
+            ("name".to_string(), "api".to_string()),
+            ("port".to_string(), "8080".to_string()),
+        ]));
+        assert_eq!(
+            renderer.render("{{name}} on {{ port }}, {{name}}"),
+            "api on 8080, api"
+        );
+        assert_eq!(
+            renderer.render("token: ${{ secrets.TOKEN }}"),
+            "token: ${{ secrets.TOKEN }}"
+        );
+        assert_eq!(renderer.render("{{name}} {{name"), "api {{name");
+        assert_eq!(Renderer::default().render("{{name}}"), "{{name}}");
+    }
+
+    #[test]
+    fn resolve_checks_given_values_and_defaults() {
+        let variables: Vec<Variable> = vec![
+            variable(
+                "license",
+                VariableKind::Select {
+                    options: vec!["MIT".to_string(), "Apache-2.0".to_string()],
+                    default: Some("MIT".to_string()),
+                },
+            ),
+            variable("docker", VariableKind::Confirm { default: None }),
+        ];
+        let given = |values: &[(&str, &str)]| -> HashMap<String, String> {
+            values
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect()
+        };
+
+        let values = resolve(
+            &variables,
+            given(&[("docker", "true"), ("extra", "x")]),
+            false,
+        )
+        .unwrap();
+        assert_eq!(values["license"], "MIT");
+        assert_eq!(values["docker"], "true");
+        // Values the template doesn't declare are kept for rendering
+        assert_eq!(values["extra"], "x");
+
+        let err = resolve(&variables, given(&[("docker", "yes")]), false).unwrap_err();
+        assert!(matches!(err, Error::InvalidVariable(name, _) if name == "docker"));
+        let err = resolve(&variables, given(&[("license", "GPL")]), false).unwrap_err();
+        assert!(matches!(err, Error::InvalidVariable(name, _) if name == "license"));
+        let err = resolve(&variables, given(&[]), false).unwrap_err();
+        assert!(matches!(err, Error::MissingVariable(name) if name == "docker"));
+    }
+}